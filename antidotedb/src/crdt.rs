@@ -0,0 +1,69 @@
+//! The CRDT objects of AntidoteDB supported by the client.
+
+use crate::proto::Encoder;
+
+/// `CRDT_type` of a last-writer-wins register.
+const LWWREG_TYPE: u64 = 5;
+
+/// A last-writer-wins register, identified by its key and bucket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LWWREG {
+    key: String,
+    bucket: String,
+}
+
+impl LWWREG {
+    pub fn new(key: &str, bucket: &str) -> Self {
+        LWWREG {
+            key: key.to_string(),
+            bucket: bucket.to_string(),
+        }
+    }
+
+    /// Sets the register to `value`, stored in big-endian.
+    pub fn set(&self, value: u64) -> Operation {
+        Operation {
+            object: self.clone(),
+            value: value.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// The `ApbBoundObject` of the register.
+    pub(crate) fn encode(&self) -> Encoder {
+        Encoder::default()
+            .bytes(1, self.key.as_bytes())
+            .varint(2, LWWREG_TYPE)
+            .bytes(3, self.bucket.as_bytes())
+    }
+}
+
+/// An update of a register.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Operation {
+    object: LWWREG,
+    value: Vec<u8>,
+}
+
+impl Operation {
+    /// The `ApbUpdateOp` of the update, an `ApbRegUpdate` on the bound object.
+    pub(crate) fn encode(&self) -> Encoder {
+        let reg_update = Encoder::default().bytes(1, &self.value);
+        Encoder::default()
+            .message(1, self.object.encode())
+            .message(2, Encoder::default().message(3, reg_update))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_encodes_a_register_update() {
+        let op = LWWREG::new("7", "dbcop").set(1);
+        assert_eq!(
+            op.encode().finish(),
+            b"\x0a\x0c\x0a\x017\x10\x05\x1a\x05dbcop\x12\x0c\x1a\x0a\x0a\x08\x00\x00\x00\x00\x00\x00\x00\x01"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! A minimal synchronous client for the protocol buffer interface of AntidoteDB, covering
+//! interactive transactions over last-writer-wins registers.
+
+pub mod crdt;
+mod proto;
+
+use std::fmt;
+use std::io;
+use std::net::TcpStream;
+
+use crdt::{Operation, LWWREG};
+use proto::{code, Decoder, Encoder, Field};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The server rejected the request with an `ApbErrorResp`.
+    Server { code: u32, message: String },
+    /// The server reported the operation as unsuccessful, with its error code if given.
+    Failed { code: Option<u32> },
+    /// The response could not be decoded.
+    Decode(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Server { code, message } => write!(f, "error {}: {}", code, message),
+            Error::Failed { code: Some(code) } => write!(f, "operation failed with error code {}", code),
+            Error::Failed { code: None } => write!(f, "operation failed"),
+            Error::Decode(message) => write!(f, "malformed response: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// The descriptor of a running interactive transaction.
+#[derive(Clone, Debug)]
+pub struct Transaction(Vec<u8>);
+
+/// The fields common to the responses, `success` and `errorcode`.
+#[derive(Default)]
+struct Status {
+    success: bool,
+    error_code: Option<u32>,
+}
+
+impl Status {
+    fn check(self) -> Result<(), Error> {
+        if self.success {
+            Ok(())
+        } else {
+            Err(Error::Failed { code: self.error_code })
+        }
+    }
+}
+
+fn as_bool(field: &Field) -> Result<bool, Error> {
+    match field {
+        Field::Varint(value) => Ok(*value != 0),
+        Field::Bytes(_) => Err(Error::Decode("expected a boolean".to_string())),
+    }
+}
+
+fn as_u32(field: &Field) -> Result<u32, Error> {
+    match field {
+        Field::Varint(value) => Ok(*value as u32),
+        Field::Bytes(_) => Err(Error::Decode("expected an integer".to_string())),
+    }
+}
+
+fn as_bytes<'a>(field: &Field<'a>) -> Result<&'a [u8], Error> {
+    match field {
+        Field::Bytes(value) => Ok(value),
+        Field::Varint(_) => Err(Error::Decode("expected bytes".to_string())),
+    }
+}
+
+/// Decodes a response whose success and error code are fields 1 and `error_field`, handing
+/// every other field to `other`.
+fn decode_status<'a>(
+    message: &'a [u8],
+    error_field: u64,
+    mut other: impl FnMut(u64, Field<'a>) -> Result<(), Error>,
+) -> Result<Status, Error> {
+    let mut status = Status::default();
+    let mut decoder = Decoder::new(message);
+    while let Some((number, field)) = decoder.field()? {
+        match number {
+            1 => status.success = as_bool(&field)?,
+            n if n == error_field => status.error_code = Some(as_u32(&field)?),
+            _ => other(number, field)?,
+        }
+    }
+    Ok(status)
+}
+
+/// A connection to an AntidoteDB node.
+#[derive(Debug)]
+pub struct AntidoteDB {
+    stream: TcpStream,
+}
+
+impl AntidoteDB {
+    /// Connects to the protocol buffer port of a node, given as `host:port`.
+    pub fn connect_with_string(addr: &str) -> Result<Self, Error> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(AntidoteDB { stream })
+    }
+
+    /// Sends a request and returns the response, which must have the `expected` code.
+    fn request(&mut self, code: u8, message: Encoder, expected: u8) -> Result<Vec<u8>, Error> {
+        proto::send(&mut self.stream, code, &message.finish())?;
+        let (code, response) = proto::receive(&mut self.stream)?;
+        if code == code::ERROR_RESP {
+            let mut error = (0, String::new());
+            let mut decoder = Decoder::new(&response);
+            while let Some((number, field)) = decoder.field()? {
+                match number {
+                    1 => error.1 = String::from_utf8_lossy(as_bytes(&field)?).into_owned(),
+                    2 => error.0 = as_u32(&field)?,
+                    _ => {}
+                }
+            }
+            return Err(Error::Server { code: error.0, message: error.1 });
+        }
+        if code != expected {
+            return Err(Error::Decode(format!("unexpected message code {}", code)));
+        }
+        Ok(response)
+    }
+
+    /// Starts a transaction reading a snapshot no older than the commit time `timestamp`.
+    pub fn start_transaction(&mut self, timestamp: Option<&Vec<u8>>) -> Result<Transaction, Error> {
+        let mut message = Encoder::default();
+        if let Some(timestamp) = timestamp {
+            message = message.bytes(1, timestamp);
+        }
+        let response = self.request(code::START_TRANSACTION, message, code::START_TRANSACTION_RESP)?;
+        let mut descriptor = None;
+        decode_status(&response, 3, |number, field| {
+            if number == 2 {
+                descriptor = Some(as_bytes(&field)?.to_vec());
+            }
+            Ok(())
+        })?
+        .check()?;
+        descriptor
+            .map(Transaction)
+            .ok_or_else(|| Error::Decode("no transaction descriptor".to_string()))
+    }
+
+    pub fn mult_update_in_transaction(&mut self, ops: &[Operation], transaction: &Transaction) -> Result<(), Error> {
+        let message = ops
+            .iter()
+            .fold(Encoder::default(), |message, op| message.message(1, op.encode()))
+            .bytes(2, &transaction.0);
+        let response = self.request(code::UPDATE_OBJECTS, message, code::OPERATION_RESP)?;
+        decode_status(&response, 2, |_, _| Ok(()))?.check()
+    }
+
+    /// Reads the registers, returning the value of each, empty if it was never set.
+    pub fn mult_read_in_transaction(&mut self, objects: &[LWWREG], transaction: &Transaction) -> Result<Vec<Vec<u8>>, Error> {
+        let message = objects
+            .iter()
+            .fold(Encoder::default(), |message, object| message.message(1, object.encode()))
+            .bytes(2, &transaction.0);
+        let response = self.request(code::READ_OBJECTS, message, code::READ_OBJECTS_RESP)?;
+        let mut values = Vec::new();
+        decode_status(&response, 3, |number, field| {
+            if number == 2 {
+                // the `reg` field of an `ApbReadObjectResp`, holding an `ApbGetRegResp`
+                let mut value = Vec::new();
+                let mut object = Decoder::new(as_bytes(&field)?);
+                while let Some((number, field)) = object.field()? {
+                    if number == 3 {
+                        let mut reg = Decoder::new(as_bytes(&field)?);
+                        while let Some((number, field)) = reg.field()? {
+                            if number == 1 {
+                                value = as_bytes(&field)?.to_vec();
+                            }
+                        }
+                    }
+                }
+                values.push(value);
+            }
+            Ok(())
+        })?
+        .check()?;
+        if values.len() != objects.len() {
+            return Err(Error::Decode(format!("{} values for {} objects", values.len(), objects.len())));
+        }
+        Ok(values)
+    }
+
+    /// Commits the transaction, returning its commit time.
+    pub fn commit_transaction(&mut self, transaction: &Transaction) -> Result<Vec<u8>, Error> {
+        let message = Encoder::default().bytes(1, &transaction.0);
+        let response = self.request(code::COMMIT_TRANSACTION, message, code::COMMIT_RESP)?;
+        let mut commit_time = None;
+        decode_status(&response, 3, |number, field| {
+            if number == 2 {
+                commit_time = Some(as_bytes(&field)?.to_vec());
+            }
+            Ok(())
+        })?
+        .check()?;
+        commit_time.ok_or_else(|| Error::Decode("no commit time".to_string()))
+    }
+
+    pub fn abort_transaction(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        let message = Encoder::default().bytes(1, &transaction.0);
+        let response = self.request(code::ABORT_TRANSACTION, message, code::OPERATION_RESP)?;
+        decode_status(&response, 2, |_, _| Ok(()))?.check()
+    }
+}
@@ -0,0 +1,195 @@
+//! The subset of protocol buffer encoding used by the messages of AntidoteDB.
+
+use std::io::{self, Read, Write};
+
+use crate::Error;
+
+/// Message codes of the AntidoteDB protocol, each message being framed as its length,
+/// its code and its encoding.
+pub mod code {
+    pub const ERROR_RESP: u8 = 0;
+    pub const OPERATION_RESP: u8 = 111;
+    pub const READ_OBJECTS: u8 = 116;
+    pub const UPDATE_OBJECTS: u8 = 118;
+    pub const START_TRANSACTION: u8 = 119;
+    pub const ABORT_TRANSACTION: u8 = 120;
+    pub const COMMIT_TRANSACTION: u8 = 121;
+    pub const START_TRANSACTION_RESP: u8 = 124;
+    pub const READ_OBJECTS_RESP: u8 = 126;
+    pub const COMMIT_RESP: u8 = 127;
+}
+
+const VARINT: u64 = 0;
+const FIXED64: u64 = 1;
+const LENGTH_DELIMITED: u64 = 2;
+const FIXED32: u64 = 5;
+
+/// Encodes the fields of a message.
+#[derive(Default)]
+pub struct Encoder(Vec<u8>);
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+impl Encoder {
+    pub fn varint(mut self, field: u64, value: u64) -> Self {
+        put_varint(&mut self.0, field << 3 | VARINT);
+        put_varint(&mut self.0, value);
+        self
+    }
+
+    pub fn bytes(mut self, field: u64, value: &[u8]) -> Self {
+        put_varint(&mut self.0, field << 3 | LENGTH_DELIMITED);
+        put_varint(&mut self.0, value.len() as u64);
+        self.0.extend_from_slice(value);
+        self
+    }
+
+    pub fn message(self, field: u64, value: Encoder) -> Self {
+        self.bytes(field, &value.0)
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// A field of a decoded message, fixed-size fields being skipped as no message uses them.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Decodes the fields of a message in order.
+pub struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Decoder(buf)
+    }
+
+    fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.0.split_first().ok_or_else(|| truncated("varint"))?;
+            self.0 = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(Error::Decode("varint longer than 64 bits".to_string()))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(truncated("field"));
+        }
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(value)
+    }
+
+    /// The next field with its number, `None` at the end of the message.
+    pub fn field(&mut self) -> Result<Option<(u64, Field<'a>)>, Error> {
+        while !self.0.is_empty() {
+            let key = self.varint()?;
+            let field = key >> 3;
+            match key & 7 {
+                VARINT => return Ok(Some((field, Field::Varint(self.varint()?)))),
+                LENGTH_DELIMITED => {
+                    let len = self.varint()? as usize;
+                    return Ok(Some((field, Field::Bytes(self.take(len)?))));
+                }
+                FIXED64 => {
+                    self.take(8)?;
+                }
+                FIXED32 => {
+                    self.take(4)?;
+                }
+                wire_type => return Err(Error::Decode(format!("unsupported wire type {}", wire_type))),
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn truncated(what: &str) -> Error {
+    Error::Decode(format!("truncated {}", what))
+}
+
+/// Sends a message as its length, including the code, in big-endian, its code and its encoding.
+pub fn send<W: Write>(writer: &mut W, code: u8, message: &[u8]) -> io::Result<()> {
+    let len = message.len() as u32 + 1;
+    let mut frame = Vec::with_capacity(message.len() + 5);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.push(code);
+    frame.extend_from_slice(message);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Receives a message, returning its code and its encoding.
+pub fn receive<R: Read>(reader: &mut R) -> Result<(u8, Vec<u8>), Error> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 {
+        return Err(Error::Decode("empty frame".to_string()));
+    }
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame)?;
+    let message = frame.split_off(1);
+    Ok((frame[0], message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_round_trip() {
+        let inner = Encoder::default().bytes(1, b"value");
+        let message = Encoder::default()
+            .varint(1, 300)
+            .message(3, inner)
+            .bytes(4, b"")
+            .finish();
+        assert_eq!(message, b"\x08\xac\x02\x1a\x07\x0a\x05value\x22\x00");
+
+        let mut decoder = Decoder::new(&message);
+        assert_eq!(decoder.field().unwrap(), Some((1, Field::Varint(300))));
+        assert_eq!(decoder.field().unwrap(), Some((3, Field::Bytes(b"\x0a\x05value"))));
+        assert_eq!(decoder.field().unwrap(), Some((4, Field::Bytes(b""))));
+        assert_eq!(decoder.field().unwrap(), None);
+    }
+
+    #[test]
+    fn fixed_fields_are_skipped() {
+        let message = b"\x09\x01\x02\x03\x04\x05\x06\x07\x08\x15\x01\x02\x03\x04\x10\x01";
+        let mut decoder = Decoder::new(message);
+        assert_eq!(decoder.field().unwrap(), Some((2, Field::Varint(1))));
+        assert_eq!(decoder.field().unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_message_is_an_error() {
+        assert!(Decoder::new(b"\x0a\x05val").field().is_err());
+        assert!(Decoder::new(b"\x08\xac").field().is_err());
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut frame = Vec::new();
+        send(&mut frame, code::COMMIT_TRANSACTION, b"\x0a\x01t").unwrap();
+        assert_eq!(frame, b"\x00\x00\x00\x04\x79\x0a\x01t");
+        let (code, message) = receive(&mut &frame[..]).unwrap();
+        assert_eq!((code, &message[..]), (code::COMMIT_TRANSACTION, &b"\x0a\x01t"[..]));
+    }
+}
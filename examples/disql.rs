@@ -1,8 +1,4 @@
-#[macro_use]
-extern crate chrono;
 extern crate dbcop;
-
-#[macro_use]
 extern crate serde;
 
 use std::fs::File;
@@ -14,6 +10,9 @@ use std::collections::HashMap;
 
 use dbcop::consistency::Consistency;
 
+use clap::ValueEnum;
+use serde::Deserialize;
+
 pub type VarId = u64;
 pub type Value = Vec<u8>;
 pub type SetId = u64;
@@ -52,6 +51,9 @@ fn main() {
                 session.push(Transaction {
                     events: txn_deser.op.clone(),
                     success: txn_deser.committed,
+                    attempt: None,
+                    abort: None,
+                    gave_up: false,
                 })
             }
 
@@ -70,22 +72,20 @@ fn main() {
     // }
 
     // let mut count = 0;
-    for (id, hist) in histories.iter().enumerate() {
-        use std::path::PathBuf;
-        let mut verifier = Verifier::new(PathBuf::from("solve_dir"));
-        verifier.model("");
-
-        let min_level = match verifier.verify(hist) {
-            Some(Consistency::RepeatableRead) => Consistency::ReadCommitted,
-            Some(Consistency::ReadAtomic) => Consistency::RepeatableRead,
-            Some(Consistency::Causal) => Consistency::ReadAtomic,
-            Some(Consistency::Prefix) => Consistency::Causal,
-            Some(Consistency::SnapshotIsolation) => Consistency::Prefix,
-            Some(Consistency::Serializable) => Consistency::SnapshotIsolation,
-            None => Consistency::Serializable,
-            _ => unreachable!(),
-        };
-        println!("{:?}", min_level);
+    for hist in histories.iter() {
+        let mut strongest = None;
+        // these histories carry no timing, so real-time order is unknown
+        let levels = Consistency::value_variants().iter().filter(|&&level| level != Consistency::StrictSerializable);
+        for &level in levels {
+            match Verifier::new(level).verify(hist) {
+                Ok(()) => strongest = Some(level),
+                Err(violation) => {
+                    println!("{}", violation);
+                    break;
+                }
+            }
+        }
+        println!("{:?}", strongest);
     }
     // println!("bad histories {}/{}", count, histories.len());
 }
@@ -0,0 +1,54 @@
+use crate::db::history::{Abort, AbortKind};
+
+/// Classifies an error of a PostgreSQL compatible database by its SQLSTATE.
+pub fn postgres_abort(e: &postgres::Error) -> Abort {
+    let code = e.code().map(|state| state.code().to_string());
+    let kind = match code.as_deref() {
+        Some("40001") => AbortKind::SerializationFailure,
+        Some("40P01") => AbortKind::Deadlock,
+        Some("55P03") => AbortKind::LockWaitTimeout,
+        Some(state) if state.starts_with("08") => AbortKind::ConnectionLoss,
+        None if e.is_closed() => AbortKind::ConnectionLoss,
+        _ => AbortKind::Other,
+    };
+    Abort { kind, code, message: e.to_string() }
+}
+
+/// Classifies an error of a MySQL compatible database by its error code.
+pub fn mysql_abort(e: &mysql::Error) -> Abort {
+    match e {
+        mysql::Error::MySqlError(error) => {
+            let kind = match (error.code, error.state.as_str()) {
+                (1213, _) => AbortKind::Deadlock,
+                (1205, _) => AbortKind::LockWaitTimeout,
+                // TiDB's write conflict of optimistic transactions
+                (9007, _) => AbortKind::CommitConflict,
+                (_, "40001") => AbortKind::SerializationFailure,
+                (_, state) if state.starts_with("08") => AbortKind::ConnectionLoss,
+                _ => AbortKind::Other,
+            };
+            Abort { kind, code: Some(error.code.to_string()), message: error.message.clone() }
+        }
+        mysql::Error::IoError(_) => Abort { kind: AbortKind::ConnectionLoss, code: None, message: e.to_string() },
+        _ => Abort { kind: AbortKind::Other, code: None, message: e.to_string() },
+    }
+}
+
+/// Classifies an error of SQLite by its extended result code.
+pub fn sqlite_abort(e: &rusqlite::Error) -> Abort {
+    match e {
+        rusqlite::Error::SqliteFailure(error, _) => {
+            let kind = match error.code {
+                // a deferred transaction cannot write after another one committed since it read
+                rusqlite::ErrorCode::DatabaseBusy if error.extended_code == rusqlite::ffi::SQLITE_BUSY_SNAPSHOT => {
+                    AbortKind::SerializationFailure
+                }
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked => AbortKind::LockWaitTimeout,
+                rusqlite::ErrorCode::CannotOpen => AbortKind::ConnectionLoss,
+                _ => AbortKind::Other,
+            };
+            Abort { kind, code: Some(error.extended_code.to_string()), message: e.to_string() }
+        }
+        _ => Abort { kind: AbortKind::Other, code: None, message: e.to_string() },
+    }
+}
@@ -0,0 +1,207 @@
+use crate::db::cluster::{Cluster, ClusterNode, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{Abort, AbortKind, HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
+
+use antidotedb::crdt::LWWREG;
+use antidotedb::{AntidoteDB, Error};
+
+/// The `aborted` error code of AntidoteDB.
+const ABORTED: u32 = 3;
+
+/// Classifies an AntidoteDB error by its error code.
+fn antidote_abort(e: &Error) -> Abort {
+    let (kind, code) = match e {
+        Error::Io(_) => (AbortKind::ConnectionLoss, None),
+        Error::Failed { code: Some(ABORTED) } => (AbortKind::CommitConflict, Some(ABORTED)),
+        Error::Failed { code } => (AbortKind::Other, *code),
+        Error::Server { code, .. } => (AbortKind::Other, Some(*code)),
+        Error::Decode(_) => (AbortKind::Other, None),
+    };
+    Abort { kind, code: code.map(|code| code.to_string()), message: e.to_string() }
+}
+
+fn register(variable: usize) -> LWWREG {
+    LWWREG::new(&format!("{}", variable), "dbcop")
+}
+
+/// The value of a register, which is empty if the variable was never created.
+fn value(variable: usize, bytes: &[u8]) -> Result<usize, DbcopError> {
+    let bytes: [u8; 8] = bytes
+        .try_into()
+        .map_err(|_| DbcopError::Execution(format!("variable {} does not exist", variable)))?;
+    Ok(u64::from_be_bytes(bytes) as usize)
+}
+
+#[derive(Debug, Clone)]
+pub struct AntidoteNode {
+    node: Node,
+    addr: String,
+    id: usize,
+    /// The commit time of the variable creation, the first transaction of the session must see.
+    timestamp: Option<Vec<u8>>,
+}
+
+impl From<Node> for AntidoteNode {
+    fn from(node: Node) -> Self {
+        AntidoteNode {
+            node: node.clone(),
+            addr: node.addr.to_string(),
+            id: node.id,
+            timestamp: None,
+        }
+    }
+}
+
+impl ClusterNode for AntidoteNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let connection = |e: Error| DbcopError::Connection(format!("node {}: {}", self.id, e));
+        let mut conn = AntidoteDB::connect_with_string(&self.addr).map_err(connection)?;
+
+        // every transaction starts after the commit of the previous one of the session
+        let mut timestamp = self.timestamp.clone();
+
+        let mut recorder = SessionRecorder::new(retry);
+        for transaction in hist.iter_mut() {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
+                transaction.success = true;
+                match conn.start_transaction(timestamp.as_ref()) {
+                    Ok(db_transaction) => {
+                        for event in transaction.events.iter_mut() {
+                            let obj = register(event.variable);
+                            let result = if event.write {
+                                conn.mult_update_in_transaction(&[obj.set(event.value as u64)], &db_transaction)
+                            } else {
+                                match conn.mult_read_in_transaction(&[obj], &db_transaction) {
+                                    Ok(values) => {
+                                        event.value = value(event.variable, &values[0])?;
+                                        Ok(())
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            };
+                            match result {
+                                Ok(()) => event.success = true,
+                                Err(e) => {
+                                    transaction.success = false;
+                                    transaction.abort = Some(antidote_abort(&e));
+                                    break;
+                                }
+                            }
+                        }
+
+                        if transaction.success {
+                            match conn.commit_transaction(&db_transaction) {
+                                Ok(commit_time) => timestamp = Some(commit_time),
+                                Err(e) => {
+                                    transaction.success = false;
+                                    transaction.abort = Some(antidote_abort(&e));
+                                }
+                            }
+                        } else if !matches!(transaction.abort, Some(Abort { kind: AbortKind::ConnectionLoss, .. })) {
+                            // the server may have aborted the transaction already
+                            let _ = conn.abort_transaction(&db_transaction);
+                        }
+                    }
+                    Err(e) => {
+                        transaction.success = false;
+                        transaction.abort = Some(antidote_abort(&e));
+                    }
+                }
+                // a connection broken by a failed request cannot send any other one
+                if matches!(transaction.abort, Some(Abort { kind: AbortKind::ConnectionLoss, .. })) {
+                    conn = AntidoteDB::connect_with_string(&self.addr).map_err(connection)?;
+                }
+                recorder.end_attempt(transaction, start);
+            }
+        }
+        recorder.finish(hist);
+        Ok(())
+    }
+}
+
+/// AntidoteDB nodes reached over their protocol buffer interface, every variable a
+/// last-writer-wins register of the `dbcop` bucket.
+#[derive(Debug)]
+pub struct AntidoteCluster(Vec<AntidoteNode>);
+
+impl AntidoteCluster {
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        let nodes = AntidoteCluster::node_vec(ips)?;
+        Ok(AntidoteCluster(nodes.into_iter().map(From::from).collect()))
+    }
+
+    fn connect(addr: &str) -> Result<AntidoteDB, DbcopError> {
+        AntidoteDB::connect_with_string(addr).map_err(|e| DbcopError::Connection(e.to_string()))
+    }
+
+    fn create_table(&self) -> Result<(), DbcopError> {
+        Ok(())
+    }
+
+    fn create_variables(&mut self, n_variable: usize) -> Result<(), DbcopError> {
+        let setup = |e: Error| DbcopError::Setup(e.to_string());
+        let addr = self
+            .get_antidote_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        let mut conn = AntidoteCluster::connect(&addr)?;
+
+        let db_transaction = conn.start_transaction(None).map_err(setup)?;
+        let ops: Vec<_> = (0..n_variable).map(|variable| register(variable).set(0)).collect();
+        conn.mult_update_in_transaction(&ops, &db_transaction).map_err(setup)?;
+        let commit_time = conn.commit_transaction(&db_transaction).map_err(setup)?;
+        self.0.iter_mut().for_each(|x| {
+            x.timestamp = Some(commit_time.clone());
+        });
+
+        // every node must see the variables before the sessions start
+        for x in self.0.iter() {
+            let mut conn = AntidoteCluster::connect(&x.addr)?;
+            let db_transaction = conn.start_transaction(x.timestamp.as_ref()).map_err(setup)?;
+            let objs: Vec<_> = (0..n_variable).map(register).collect();
+            let values = conn.mult_read_in_transaction(&objs, &db_transaction).map_err(setup)?;
+            let zero = values
+                .iter()
+                .enumerate()
+                .all(|(variable, bytes)| matches!(value(variable, bytes), Ok(0)));
+            if !zero {
+                return Err(DbcopError::Setup(format!("variables are not zero on node {}", x.id)));
+            }
+            conn.commit_transaction(&db_transaction).map_err(setup)?;
+        }
+        Ok(())
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        Ok(())
+    }
+
+    fn get_antidote_addr(&self, i: usize) -> Option<String> {
+        self.0.get(i).map(|node| node.addr.clone())
+    }
+}
+
+impl Cluster<AntidoteNode> for AntidoteCluster {
+    fn n_node(&self) -> usize {
+        self.0.len()
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        self.create_table()
+    }
+    fn get_node(&self, id: usize) -> Node {
+        self.0[id].node.clone()
+    }
+    fn get_cluster_node(&self, id: usize) -> AntidoteNode {
+        self.0[id].clone()
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
+    }
+    fn info(&self) -> String {
+        "AntidoteDB".to_string()
+    }
+}
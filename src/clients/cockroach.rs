@@ -0,0 +1,171 @@
+use rand::Rng;
+
+use crate::clients::postgres_abort;
+use crate::db::cluster::{Cluster, ClusterNode, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
+
+use postgres::{Client, NoTls};
+
+#[derive(Debug)]
+pub struct CockroachNode {
+    addr: String,
+    id: usize,
+}
+
+impl From<Node> for CockroachNode {
+    fn from(node: Node) -> Self {
+        CockroachNode {
+            addr: format!("postgresql://{}@{}", "root", node.addr),
+            id: node.id,
+        }
+    }
+}
+
+impl ClusterNode for CockroachNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let mut rng = rand::thread_rng();
+        let mut conn = Client::connect(self.addr.as_str(), NoTls)
+            .map_err(|e| DbcopError::Connection(format!("node {}: {}", self.id, e)))?;
+
+        let mut recorder = SessionRecorder::new(retry);
+        for transaction in hist.iter_mut() {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
+                transaction.success = true;
+                match conn
+                    .build_transaction()
+                    .isolation_level(postgres::IsolationLevel::Serializable)
+                    .start()
+                {
+                    Ok(mut sqltxn) => {
+                        for event in transaction.events.iter_mut() {
+                            let result = if event.write {
+                                sqltxn
+                                    .execute(
+                                        "UPDATE dbcop.variables SET val=$1 WHERE var=$2",
+                                        &[&(event.value as i64), &(event.variable as i64)],
+                                    )
+                                    .map(|_| ())
+                            } else {
+                                match sqltxn
+                                    .query("SELECT * FROM dbcop.variables WHERE var=$1", &[&(event.variable as i64)])
+                                {
+                                    Ok(result) => {
+                                        // every variable is created before the history is executed
+                                        let row = result.first().ok_or_else(|| {
+                                            DbcopError::Execution(format!("variable {} does not exist", event.variable))
+                                        })?;
+                                        event.value = row.get::<_, i64>("val") as usize;
+                                        Ok(())
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            };
+                            match result {
+                                Ok(()) => event.success = true,
+                                Err(e) => {
+                                    // a failed statement aborts the transaction
+                                    transaction.success = false;
+                                    transaction.abort = Some(postgres_abort(&e));
+                                    break;
+                                }
+                            }
+                        }
+
+                        if transaction.success {
+                            if let Err(e) = sqltxn.commit() {
+                                transaction.success = false;
+                                transaction.abort = Some(postgres_abort(&e));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        transaction.success = false;
+                        transaction.abort = Some(postgres_abort(&e));
+                    }
+                }
+                recorder.end_attempt(transaction, start);
+
+                std::thread::sleep(std::time::Duration::from_millis(rng.gen_range(100..1000)));
+            }
+        }
+        recorder.finish(hist);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CockroachCluster(Vec<Node>);
+
+impl CockroachCluster {
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        Ok(CockroachCluster(CockroachCluster::node_vec(ips)?))
+    }
+
+    fn connect(&self) -> Result<Client, DbcopError> {
+        let ip = self
+            .get_postgresql_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        Client::connect(ip.as_str(), NoTls).map_err(|e| DbcopError::Connection(e.to_string()))
+    }
+
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: postgres::Error| DbcopError::Setup(e.to_string());
+        let mut pool = self.connect()?;
+        pool.execute("CREATE DATABASE IF NOT EXISTS dbcop",  &[]).map_err(setup)?;
+        pool.execute("DROP TABLE IF EXISTS dbcop.variables",  &[]).map_err(setup)?;
+        pool.execute(
+            "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT NOT NULL PRIMARY KEY, val INT NOT NULL)", &[]
+        ).map_err(setup)?;
+        Ok(())
+    }
+
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let setup = |e: postgres::Error| DbcopError::Setup(e.to_string());
+        let mut conn = self.connect()?;
+        let stmt = conn
+            .prepare("INSERT INTO dbcop.variables (var, val) values ($1, 0)")
+            .map_err(setup)?;
+        for variable in 0..n_variable {
+            conn.execute(&stmt, &[&(variable as i64)]).map_err(setup)?;
+        }
+        Ok(())
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.execute("DROP DATABASE dbcop CASCADE", &[])
+            .map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
+    }
+
+    fn get_postgresql_addr(&self, i: usize) -> Option<String> {
+        self.0.get(i).map(|node| format!("postgresql://{}@{}", "root", node.addr))
+    }
+}
+
+impl Cluster<CockroachNode> for CockroachCluster {
+    fn n_node(&self) -> usize {
+        self.0.len()
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        self.create_table()
+    }
+    fn get_node(&self, id: usize) -> Node {
+        self.0[id].clone()
+    }
+    fn get_cluster_node(&self, id: usize) -> CockroachNode {
+        From::from(self.get_node(id))
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
+    }
+    fn info(&self) -> String {
+        "CockroachDB".to_string()
+    }
+}
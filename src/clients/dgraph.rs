@@ -1,10 +1,13 @@
+use std::fmt;
 use std::fs;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::collections::HashMap;
 
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::error::DbcopError;
+use crate::db::history::{Abort, AbortKind, HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
 
 use clap::{App, Arg};
 
@@ -29,12 +32,28 @@ struct All {
     all: Vec<KeyValuePair>,
 }
 
-impl From<Response> for All {
-    fn from(r: Response) -> Self {
-        serde_json::from_slice(&r.json).unwrap()
+impl TryFrom<Response> for All {
+    type Error = serde_json::Error;
+
+    fn try_from(r: Response) -> Result<Self, Self::Error> {
+        serde_json::from_slice(&r.json)
     }
 }
 
+/// Classifies a Dgraph error by its message and the messages of its causes, the client
+/// exposes no error codes.
+fn dgraph_abort<E: fmt::Debug + fmt::Display>(e: &E) -> Abort {
+    let causes = format!("{:?}", e);
+    let kind = if causes.contains("Transaction has been aborted") {
+        AbortKind::CommitConflict
+    } else if causes.contains("transport error") || causes.contains("Unavailable") {
+        AbortKind::ConnectionLoss
+    } else {
+        AbortKind::Other
+    };
+    Abort { kind, code: None, message: e.to_string() }
+}
+
 impl From<Node> for DGraphNode {
     fn from(node: Node) -> Self {
         DGraphNode {
@@ -45,36 +64,57 @@ impl From<Node> for DGraphNode {
 }
 
 impl ClusterNode for DGraphNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
-        let client = Client::new(format!("http://{}", self.addr)).unwrap();
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let client = Client::new(format!("http://{}", self.addr))
+            .map_err(|e| DbcopError::Connection(format!("node {}: {}", self.id, e)))?;
 
+        let mut recorder = SessionRecorder::new(retry);
         for transaction in hist.iter_mut() {
-            while !transaction.success {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
                 let mut txn = client.new_mutated_txn();
 
+                transaction.success = true;
                 for event in transaction.events.iter_mut() {
                     if event.write {
                         let mut mu = Mutation::new();
-                        mu.set_set_json(&KeyValuePair { uid: (event.variable + 1).to_string(), val: event.value }).expect("set_set_json");
-                        txn.mutate(mu).unwrap();
+                        mu.set_set_json(&KeyValuePair { uid: (event.variable + 1).to_string(), val: event.value })
+                            .map_err(|e| DbcopError::Execution(e.to_string()))?;
+                        if let Err(e) = txn.mutate(mu) {
+                            transaction.success = false;
+                            transaction.abort = Some(dgraph_abort(&e));
+                            break;
+                        }
                         event.success = true;
                     } else {
-                        let result = txn.query(format!("query {{ all(func: uid({})) {{ uid, val }} }}", event.variable + 1));
-                        let all: All = result.unwrap().try_into().unwrap();
-                        event.value = all.all[0].val as usize;
+                        let result = match txn.query(format!("query {{ all(func: uid({})) {{ uid, val }} }}", event.variable + 1)) {
+                            Ok(result) => result,
+                            Err(e) => {
+                                transaction.success = false;
+                                transaction.abort = Some(dgraph_abort(&e));
+                                break;
+                            }
+                        };
+                        let all: All = result.try_into().map_err(|e: serde_json::Error| DbcopError::Execution(e.to_string()))?;
+                        let kv = all.all.first().ok_or_else(|| {
+                            DbcopError::Execution(format!("variable {} does not exist", event.variable))
+                        })?;
+                        event.value = kv.val;
                         event.success = true;
                     }
                 }
 
-                transaction.success = match txn.commit() {
-                    Ok(_) => true,
-                    Err(_e) => {
-                        // println!("{:?} -- COMMIT ERROR {}", transaction, _e.root_cause());
-                        false
-                    },
-                };
+                if transaction.success {
+                    if let Err(e) = txn.commit() {
+                        transaction.success = false;
+                        transaction.abort = Some(dgraph_abort(&e));
+                    }
+                }
+                recorder.end_attempt(transaction, start);
             }
         }
+        recorder.finish(hist);
+        Ok(())
     }
 }
 
@@ -82,18 +122,24 @@ impl ClusterNode for DGraphNode {
 pub struct DGraphCluster(Vec<Node>);
 
 impl DGraphCluster {
-    pub fn new(ips: &Vec<&str>) -> Self {
-        DGraphCluster(DGraphCluster::node_vec(ips))
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        Ok(DGraphCluster(DGraphCluster::node_vec(ips)?))
+    }
+
+    fn connect(&self) -> Result<Client, DbcopError> {
+        let addr = self
+            .get_dgraph_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        Client::new(format!("http://{}", addr)).map_err(|e| DbcopError::Connection(e.to_string()))
     }
 
-    fn create_table(&self) -> bool {
-        let http_addr = format!("http://{}", self.get_dgraph_addr(0).unwrap());
-        let client = Client::new(http_addr).unwrap();
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let client = self.connect()?;
 
         client.alter(Operation {
             drop_all: true,
             ..Default::default()
-        }).expect("alter");
+        }).map_err(|e| DbcopError::Setup(e.to_string()))?;
 
         client.alter(Operation {
             schema: r#"
@@ -104,27 +150,28 @@ type KV {
 }
 "#.to_string(),
             ..Default::default()
-        }).expect("alter");
+        }).map_err(|e| DbcopError::Setup(e.to_string()))?;
 
-        true
+        Ok(())
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        let client = Client::new(format!("http://{}", self.get_dgraph_addr(0).unwrap())).unwrap();
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let client = self.connect()?;
         let mut txn = client.new_mutated_txn();
         let data = All { all: (1..n_variable+1).map(|uid| KeyValuePair { uid: uid.to_string(), val: 0 }).collect() };
         let mut mu = Mutation::new();
-        mu.set_set_json(&data).unwrap();
-        txn.mutate(mu).unwrap();
-        txn.commit().unwrap();
+        mu.set_set_json(&data).map_err(|e| DbcopError::Setup(e.to_string()))?;
+        txn.mutate(mu).map_err(|e| DbcopError::Setup(e.to_string()))?;
+        txn.commit().map_err(|e| DbcopError::Setup(e.to_string()))
     }
 
-    fn drop_database(&self) {
-        let client = Client::new(format!("http://{}", self.get_dgraph_addr(0).unwrap())).unwrap();
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let client = self.connect()?;
         client.alter(Operation {
             drop_all: true,
             ..Default::default()
-        }).expect("alter");
+        }).map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
     }
 
     fn get_dgraph_addr(&self, i: usize) -> Option<SocketAddr> {
@@ -136,7 +183,7 @@ impl Cluster<DGraphNode> for DGraphCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
@@ -145,11 +192,11 @@ impl Cluster<DGraphNode> for DGraphCluster {
     fn get_cluster_node(&self, id: usize) -> DGraphNode {
         From::from(self.get_node(id))
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
         "Dgraph".to_string()
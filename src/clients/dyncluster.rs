@@ -16,8 +16,8 @@ pub struct DynNode {
 }
 
 impl ClusterNode for DynNode {
-    fn exec_session(&self, hist: &mut crate::db::history::Session) {
-        self.node.exec_session(hist)
+    fn exec_session(&self, hist: &mut crate::db::history::Session, retry: &crate::db::retry::RetryPolicy) -> Result<(), crate::db::error::DbcopError> {
+        self.node.exec_session(hist, retry)
     }
 }
 
@@ -30,11 +30,11 @@ where
         self.cluster.n_node()
     }
 
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), crate::db::error::DbcopError> {
         self.cluster.setup()
     }
 
-    fn setup_test(&mut self, p: &crate::db::history::HistParams) {
+    fn setup_test(&mut self, p: &crate::db::history::HistParams) -> Result<(), crate::db::error::DbcopError> {
         self.cluster.setup_test(p)
     }
 
@@ -48,7 +48,7 @@ where
         }
     }
 
-    fn cleanup(&self) {
+    fn cleanup(&self) -> Result<(), crate::db::error::DbcopError> {
         self.cluster.cleanup()
     }
 
@@ -1,5 +1,8 @@
+use crate::clients::mysql_abort;
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::error::DbcopError;
+use crate::db::history::{AbortKind, HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
 
 use mysql::{Conn, TxOpts, prelude::*};
 
@@ -17,45 +20,76 @@ impl From<Node> for GaleraNode {
 }
 
 impl ClusterNode for GaleraNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
-        let mut conn = Conn::new(self.addr.as_str()).unwrap();
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let mut conn = Conn::new(self.addr.as_str()).map_err(|e| DbcopError::Connection(e.to_string()))?;
         let txnopts = TxOpts::default()
             .set_isolation_level(Some(mysql::IsolationLevel::RepeatableRead))
             .set_access_mode(Some(mysql::AccessMode::ReadWrite))
             .set_with_consistent_snapshot(true);
-        let read_stmt = conn.prep("SELECT * FROM dbcop.variables WHERE var=?").unwrap();
-        let write_stmt = conn.prep("UPDATE dbcop.variables SET val=? WHERE var=?").unwrap();
+        let read_stmt = conn.prep("SELECT * FROM dbcop.variables WHERE var=?")
+            .map_err(|e| DbcopError::Execution(e.to_string()))?;
+        let write_stmt = conn.prep("UPDATE dbcop.variables SET val=? WHERE var=?")
+            .map_err(|e| DbcopError::Execution(e.to_string()))?;
 
+        let mut recorder = SessionRecorder::new(retry);
         for transaction in hist.iter_mut() {
-            while !transaction.success {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
                 transaction.success = true;
-                let mut sqltxn = conn.start_transaction(txnopts).unwrap();
+                let mut sqltxn = match conn.start_transaction(txnopts) {
+                    Ok(sqltxn) => sqltxn,
+                    Err(e) => {
+                        transaction.success = false;
+                        transaction.abort = Some(mysql_abort(&e));
+                        recorder.end_attempt(transaction, start);
+                        continue;
+                    }
+                };
 
                 for event in transaction.events.iter_mut() {
                     if event.write {
-                        if let Err(_) = sqltxn.exec_drop(&write_stmt, (event.value, event.variable)) {
+                        if let Err(e) = sqltxn.exec_drop(&write_stmt, (event.value, event.variable)) {
                             transaction.success = false;
+                            transaction.abort = Some(mysql_abort(&e));
                             break;
                         }
                         event.success = true;
                     } else {
                         match sqltxn.exec_first(&read_stmt, (event.variable,)) {
                             Ok(result) => {
-                                let mut row: mysql::Row = result.unwrap();
-                                event.value = row.take("val").unwrap();
+                                let mut row: mysql::Row = result.ok_or_else(|| {
+                                    DbcopError::Execution(format!("variable {} does not exist", event.variable))
+                                })?;
+                                event.value = row.take("val").ok_or_else(|| {
+                                    DbcopError::Execution(format!("variable {} has no value", event.variable))
+                                })?;
                                 event.success = true;
                             },
-                            Err(_) => {
+                            Err(e) => {
                                 transaction.success = false;
+                                transaction.abort = Some(mysql_abort(&e));
                                 break;
                             }
                         }
                     }
                 }
 
-                transaction.success = transaction.success && sqltxn.commit().is_ok();
+                if transaction.success {
+                    if let Err(e) = sqltxn.commit() {
+                        transaction.success = false;
+                        let mut abort = mysql_abort(&e);
+                        // Galera reports a failed certification of the write set as a deadlock
+                        if abort.kind == AbortKind::Deadlock {
+                            abort.kind = AbortKind::CertificationFailure;
+                        }
+                        transaction.abort = Some(abort);
+                    }
+                }
+                recorder.end_attempt(transaction, start);
             }
         }
+        recorder.finish(hist);
+        Ok(())
     }
 }
 
@@ -63,37 +97,42 @@ impl ClusterNode for GaleraNode {
 pub struct GaleraCluster(Vec<Node>);
 
 impl GaleraCluster {
-    pub fn new(ips: &Vec<&str>) -> Self {
-        GaleraCluster(GaleraCluster::node_vec(ips))
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        Ok(GaleraCluster(GaleraCluster::node_vec(ips)?))
     }
 
-    fn create_table(&self) -> bool {
-        let addr = self.get_mysql_addr(0).unwrap();
-        let mut conn = mysql::Conn::new(addr.as_str()).unwrap();
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: mysql::Error| DbcopError::Setup(e.to_string());
+        let mut conn = self.connect()?;
 
-        conn.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).unwrap();
-        conn.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).unwrap();
+        conn.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).map_err(setup)?;
+        conn.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).map_err(setup)?;
         conn.exec_drop(
             "CREATE TABLE IF NOT EXISTS dbcop.variables (var BIGINT(64) UNSIGNED NOT NULL PRIMARY KEY, val BIGINT(64) UNSIGNED NOT NULL)", ()
-        ).unwrap();
-        true
+        ).map_err(setup)?;
+        Ok(())
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        let addr = self.get_mysql_addr(0).unwrap();
-        let mut conn = mysql::Conn::new(addr.as_str()).unwrap();
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
 
         conn.exec_batch(
             "INSERT INTO dbcop.variables (var, val) values (?, 0)",
             (0..n_variable).map(|v| (v,))
-        ).unwrap();
+        ).map_err(|e| DbcopError::Setup(e.to_string()))
     }
 
-    fn drop_database(&self) {
-        let addr = self.get_mysql_addr(0).unwrap();
-        let mut conn = mysql::Conn::new(addr.as_str()).unwrap();
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+
+        conn.exec_drop("DROP DATABASE dbcop", ()).map_err(|e| DbcopError::Setup(e.to_string()))
+    }
 
-        conn.exec_drop("DROP DATABASE dbcop", ()).unwrap();
+    fn connect(&self) -> Result<Conn, DbcopError> {
+        let addr = self
+            .get_mysql_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        Conn::new(addr.as_str()).map_err(|e| DbcopError::Connection(e.to_string()))
     }
 
     fn get_mysql_addr(&self, i: usize) -> Option<String> {
@@ -108,7 +147,7 @@ impl Cluster<GaleraNode> for GaleraCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
@@ -117,11 +156,11 @@ impl Cluster<GaleraNode> for GaleraCluster {
     fn get_cluster_node(&self, id: usize) -> GaleraNode {
         From::from(self.get_node(id))
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
         "Galera".to_string()
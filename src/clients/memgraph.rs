@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use crate::db::cluster::{Cluster, ClusterNode, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{Abort, AbortKind, HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
+
+use rsmgclient::{ConnectParams, Connection, ConnectionStatus, MgError, QueryParam, Record, Value};
+
+/// Classifies a Memgraph error by its message, the client exposes no error codes.
+fn memgraph_abort(e: &MgError) -> Abort {
+    let message = e.to_string();
+    let kind = if message.contains("conflicting transactions") {
+        AbortKind::CommitConflict
+    } else if message.contains("connect") || message.contains("receive") || message.contains("send") {
+        AbortKind::ConnectionLoss
+    } else {
+        AbortKind::Other
+    };
+    Abort { kind, code: None, message }
+}
+
+/// Connects to `addr`, every query of the connection running in an explicit transaction unless
+/// `autocommit` is set.
+fn connect(addr: SocketAddr, autocommit: bool) -> Result<Connection, MgError> {
+    Connection::connect(&ConnectParams {
+        host: Some(addr.ip().to_string()),
+        port: addr.port(),
+        lazy: false,
+        autocommit,
+        ..Default::default()
+    })
+}
+
+/// Runs `query` and fetches all its records, so that the connection can run the next one.
+fn run(conn: &mut Connection, query: &str, params: &[(&str, i64)]) -> Result<Vec<Record>, MgError> {
+    let params: HashMap<String, QueryParam> = params
+        .iter()
+        .map(|(name, value)| (name.to_string(), QueryParam::Int(*value)))
+        .collect();
+    conn.execute(query, Some(&params))?;
+    conn.fetchall()
+}
+
+/// The value of `variable` returned by a query, every variable being created before the history
+/// is executed.
+fn value(variable: usize, records: &[Record]) -> Result<usize, DbcopError> {
+    match records.first().and_then(|record| record.values.first()) {
+        Some(Value::Int(value)) => Ok(*value as usize),
+        Some(value) => Err(DbcopError::Execution(format!(
+            "variable {} has the non-integer value {:?}",
+            variable, value
+        ))),
+        None => Err(DbcopError::Execution(format!("variable {} does not exist", variable))),
+    }
+}
+
+#[derive(Debug)]
+pub struct MemgraphNode {
+    addr: SocketAddr,
+    id: usize,
+}
+
+impl From<Node> for MemgraphNode {
+    fn from(node: Node) -> Self {
+        MemgraphNode {
+            addr: node.addr,
+            id: node.id,
+        }
+    }
+}
+
+impl ClusterNode for MemgraphNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let connection = |e: MgError| DbcopError::Connection(format!("node {}: {}", self.id, e));
+        let mut conn = connect(self.addr, false).map_err(connection)?;
+
+        let mut recorder = SessionRecorder::new(retry);
+        for transaction in hist.iter_mut() {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
+                transaction.success = true;
+
+                // the client begins the transaction with the first query
+                for event in transaction.events.iter_mut() {
+                    let result = if event.write {
+                        run(
+                            &mut conn,
+                            "MATCH (n:Variable {var: $var}) SET n.val = $val RETURN n.val",
+                            &[("var", event.variable as i64), ("val", event.value as i64)],
+                        )
+                    } else {
+                        run(
+                            &mut conn,
+                            "MATCH (n:Variable {var: $var}) RETURN n.val",
+                            &[("var", event.variable as i64)],
+                        )
+                    };
+                    match result {
+                        Ok(records) => {
+                            // a write returns the value it set, so that it fails on a missing node too
+                            let value = value(event.variable, &records)?;
+                            if !event.write {
+                                event.value = value;
+                            }
+                            event.success = true;
+                        }
+                        Err(e) => {
+                            transaction.success = false;
+                            transaction.abort = Some(memgraph_abort(&e));
+                            break;
+                        }
+                    }
+                }
+
+                if transaction.success {
+                    if let Err(e) = conn.commit() {
+                        transaction.success = false;
+                        transaction.abort = Some(memgraph_abort(&e));
+                    }
+                } else if !matches!(conn.status(), ConnectionStatus::Bad) {
+                    // a failed query may have ended the transaction already
+                    let _ = conn.rollback();
+                }
+                // a connection broken by a failed query cannot run any other one
+                if matches!(conn.status(), ConnectionStatus::Bad) {
+                    conn = connect(self.addr, false).map_err(connection)?;
+                }
+                recorder.end_attempt(transaction, start);
+            }
+        }
+        recorder.finish(hist);
+        Ok(())
+    }
+}
+
+/// A Memgraph instance reached over Bolt, every variable a `Variable` node with a `val` property.
+#[derive(Debug)]
+pub struct MemgraphCluster(Vec<Node>);
+
+impl MemgraphCluster {
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        Ok(MemgraphCluster(MemgraphCluster::node_vec(ips)?))
+    }
+
+    fn connect(&self) -> Result<Connection, DbcopError> {
+        let addr = self
+            .0
+            .first()
+            .map(|node| node.addr)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        // index creation cannot run in an explicit transaction
+        connect(addr, true).map_err(|e| DbcopError::Connection(e.to_string()))
+    }
+
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: MgError| DbcopError::Setup(e.to_string());
+        let mut conn = self.connect()?;
+
+        run(&mut conn, "MATCH (n:Variable) DETACH DELETE n", &[]).map_err(setup)?;
+        run(&mut conn, "CREATE INDEX ON :Variable(var)", &[]).map_err(setup)?;
+        Ok(())
+    }
+
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+
+        run(
+            &mut conn,
+            "UNWIND range(0, $n - 1) AS i CREATE (:Variable {var: i, val: 0})",
+            &[("n", n_variable as i64)],
+        )
+        .map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+
+        run(&mut conn, "MATCH (n:Variable) DETACH DELETE n", &[]).map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
+    }
+}
+
+impl Cluster<MemgraphNode> for MemgraphCluster {
+    fn n_node(&self) -> usize {
+        self.0.len()
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        self.create_table()
+    }
+    fn get_node(&self, id: usize) -> Node {
+        self.0[id].clone()
+    }
+    fn get_cluster_node(&self, id: usize) -> MemgraphNode {
+        From::from(self.get_node(id))
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
+    }
+    fn info(&self) -> String {
+        "Memgraph".to_string()
+    }
+}
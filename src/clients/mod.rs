@@ -1,3 +1,4 @@
+mod abort;
 mod dgraph;
 mod postgres;
 mod postgres_ser;
@@ -8,14 +9,31 @@ mod dyncluster;
 mod galera;
 mod mysql;
 mod tdsql;
+mod simulated;
+mod sqlite;
+#[cfg(feature = "antidotedb")]
+mod antidote;
+#[cfg(feature = "cockroachdb")]
+mod cockroach;
+#[cfg(feature = "memgraph")]
+mod memgraph;
 
+pub use abort::{mysql_abort, postgres_abort, sqlite_abort};
 pub use dgraph::DGraphCluster;
 pub use crate::clients::postgres::PostgresCluster;
 pub use postgres_ser::PostgresCluster as PostgresSERCluster;
-pub use tidb::TiDBCluster;
+pub use tidb::{TiDBCluster, TiDBTxnMode};
 pub use yugabyte::YugabyteCluster;
 pub use yugabyte_ser::YugabyteCluster as YugabyteSERCluster;
 pub use galera::GaleraCluster;
 pub use crate::clients::mysql::MySQLCluster;
 pub use tdsql::TDSQLCluster;
+pub use simulated::{SimulatedCluster, SimulatedIsolation};
+pub use sqlite::{SqliteBegin, SqliteCluster};
+#[cfg(feature = "antidotedb")]
+pub use antidote::AntidoteCluster;
+#[cfg(feature = "cockroachdb")]
+pub use cockroach::CockroachCluster;
+#[cfg(feature = "memgraph")]
+pub use memgraph::MemgraphCluster;
 pub use dyncluster::{DynCluster, DynNode};
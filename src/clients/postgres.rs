@@ -4,8 +4,11 @@ use std::sync::Arc;
 use std::thread::spawn;
 use std::io::Write;
 
+use crate::clients::postgres_abort;
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::error::DbcopError;
+use crate::db::history::{HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
 
 use clap::{App, Arg};
 
@@ -31,20 +34,16 @@ impl PostgresNode {
 }
 
 impl ClusterNode for PostgresNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
         let progress = self.progress.add(ProgressBar::new(hist.len() as u64));
-        let mut conn = match Client::connect(self.addr.as_str(), NoTls) {
-            Ok(conn) => conn,
-            Err(e) => {
-                assert_eq!(false, hist.iter().fold(false, |b, t| b | t.success));
-                println!("CONNECTION ERROR {}", e);
-                return;
-            }
-        };
+        let mut conn = Client::connect(self.addr.as_str(), NoTls)
+            .map_err(|e| DbcopError::Connection(format!("node {}: {}", self.id, e)))?;
 
+        let mut recorder = SessionRecorder::new(retry);
         for transaction in progress.wrap_iter(hist.iter_mut()) {
-            while !transaction.success {
+            while recorder.next_attempt(transaction) {
                 transaction.success = true;
+                let start = Timestamp::now();
                 let mut sqltxn = match conn
                     .build_transaction()
                     .isolation_level(postgres::IsolationLevel::RepeatableRead)
@@ -52,8 +51,9 @@ impl ClusterNode for PostgresNode {
                     {
                         Ok(txn) => txn,
                         Err(e) => {
-                            println!("{:?} - TRANSACTION ERROR", e);
                             transaction.success = false;
+                            transaction.abort = Some(postgres_abort(&e));
+                            recorder.end_attempt(transaction, start);
                             continue;
                         }
                     };
@@ -69,6 +69,7 @@ impl ClusterNode for PostgresNode {
                             Err(e) => {
                                 // If an operation fails, then the whole transaction fails
                                 transaction.success = false;
+                                transaction.abort = Some(postgres_abort(&e));
                                 // eprintln!("WRITE ERR -- {:?}", e);
                                 break;
                             }
@@ -84,14 +85,14 @@ impl ClusterNode for PostgresNode {
                                     // set result to the initial value
                                     event.value = 0;
                                 } else {
-                                    let row = result.get(0);
-                                    let value: i64 = row.unwrap().get("val");
+                                    let value: i64 = result[0].get("val");
                                     event.value = value as usize;
                                 }
                                 event.success = true;
                             }
                             Err(e) => {
                                 transaction.success = false;
+                                transaction.abort = Some(postgres_abort(&e));
                                 // eprintln!("READ ERR -- {:?}", e);
                                 break;
                             }
@@ -99,15 +100,18 @@ impl ClusterNode for PostgresNode {
                     }
                 }
 
-                transaction.success &= match sqltxn.commit() {
-                    Ok(_) => true,
-                    Err(e) => {
+                if transaction.success {
+                    if let Err(e) = sqltxn.commit() {
                         // eprintln!("COMMIT ERR -- {:?}", e);
-                        false
+                        transaction.success = false;
+                        transaction.abort = Some(postgres_abort(&e));
                     }
-                };
+                }
+                recorder.end_attempt(transaction, start);
             }
         }
+        recorder.finish(hist);
+        Ok(())
     }
 }
 
@@ -115,27 +119,29 @@ impl ClusterNode for PostgresNode {
 pub struct PostgresCluster(Vec<Node>, Arc<MultiProgress>);
 
 impl PostgresCluster {
-    pub fn new(ips: &Vec<&str>) -> Self {
-        PostgresCluster(PostgresCluster::node_vec(ips), Arc::new(MultiProgress::new()))
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        Ok(PostgresCluster(PostgresCluster::node_vec(ips)?, Arc::new(MultiProgress::new())))
     }
 
-    fn create_table(&self) -> bool {
-        match self.get_postgresql_addr(0) {
-            Some(ip) => Client::connect(ip.as_str(), NoTls)
-                .and_then(|mut pool| {
-                    pool.execute("CREATE SCHEMA IF NOT EXISTS dbcop",  &[]).unwrap();
-                    pool.execute("DROP TABLE IF EXISTS dbcop.variables",  &[]).unwrap();
-                    pool.batch_execute(
-                        "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT8 NOT NULL PRIMARY KEY, val INT8 NOT NULL) PARTITION BY HASH (var);
-                         CREATE TABLE IF NOT EXISTS dbcop.variables_0 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 0);
-                         CREATE TABLE IF NOT EXISTS dbcop.variables_1 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 1);
-                         CREATE TABLE IF NOT EXISTS dbcop.variables_2 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 2);"
-                    ).unwrap();
-                    // conn.query("USE dbcop").unwrap();
-                    Ok(true)
-                }).is_ok(),
-            _ => false,
-        }
+    fn connect(&self) -> Result<Client, DbcopError> {
+        let ip = self
+            .get_postgresql_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        Client::connect(ip.as_str(), NoTls).map_err(|e| DbcopError::Connection(e.to_string()))
+    }
+
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: postgres::Error| DbcopError::Setup(e.to_string());
+        let mut pool = self.connect()?;
+        pool.execute("CREATE SCHEMA IF NOT EXISTS dbcop",  &[]).map_err(setup)?;
+        pool.execute("DROP TABLE IF EXISTS dbcop.variables",  &[]).map_err(setup)?;
+        pool.batch_execute(
+            "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT8 NOT NULL PRIMARY KEY, val INT8 NOT NULL) PARTITION BY HASH (var);
+             CREATE TABLE IF NOT EXISTS dbcop.variables_0 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 0);
+             CREATE TABLE IF NOT EXISTS dbcop.variables_1 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 1);
+             CREATE TABLE IF NOT EXISTS dbcop.variables_2 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 2);"
+        ).map_err(setup)?;
+        Ok(())
     }
 
     fn create_variables(&self, n_variable: usize) {
@@ -150,12 +156,11 @@ impl PostgresCluster {
         // }
     }
 
-    fn drop_database(&self) {
-        if let Some(ip) = self.get_postgresql_addr(0) {
-            if let Ok(mut conn) = Client::connect(ip.as_str(), NoTls) {
-                conn.execute("DROP SCHEMA dbcop CASCADE", &[]).unwrap();
-            }
-        }
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.execute("DROP SCHEMA dbcop CASCADE", &[])
+            .map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
     }
 
     fn get_postgresql_addr(&self, i: usize) -> Option<String> {
@@ -173,7 +178,7 @@ impl Cluster<PostgresNode> for PostgresCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
@@ -182,14 +187,15 @@ impl Cluster<PostgresNode> for PostgresCluster {
     fn get_cluster_node(&self, id: usize) -> PostgresNode {
         PostgresNode::new(self.get_node(id), self)
     }
-    fn setup_test(&mut self, p: &HistParams) {
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
         self.create_variables(p.get_n_variable());
 
         let progress = self.1.clone();
         spawn(move || progress.join());
+        Ok(())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
         "PostgreSQL".to_string()
@@ -4,8 +4,11 @@ use std::sync::Arc;
 use std::thread::spawn;
 use std::io::Write;
 
+use crate::clients::postgres_abort;
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::error::DbcopError;
+use crate::db::history::{HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
 
 use clap::{App, Arg};
 
@@ -31,20 +34,16 @@ impl PostgresNode {
 }
 
 impl ClusterNode for PostgresNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
         let progress = self.progress.add(ProgressBar::new(hist.len() as u64));
-        let mut conn = match Client::connect(self.addr.as_str(), NoTls) {
-            Ok(conn) => conn,
-            Err(e) => {
-                assert_eq!(false, hist.iter().fold(false, |b, t| b | t.success));
-                println!("CONNECTION ERROR {}", e);
-                return;
-            }
-        };
+        let mut conn = Client::connect(self.addr.as_str(), NoTls)
+            .map_err(|e| DbcopError::Connection(format!("node {}: {}", self.id, e)))?;
 
+        let mut recorder = SessionRecorder::new(retry);
         for transaction in progress.wrap_iter(hist.iter_mut()) {
-            while !transaction.success {
+            while recorder.next_attempt(transaction) {
                 transaction.success = true;
+                let start = Timestamp::now();
                 let mut sqltxn = match conn
                     .build_transaction()
                     .isolation_level(postgres::IsolationLevel::Serializable)
@@ -52,8 +51,9 @@ impl ClusterNode for PostgresNode {
                     {
                         Ok(txn) => txn,
                         Err(e) => {
-                            println!("{:?} - TRANSACTION ERROR", e);
                             transaction.success = false;
+                            transaction.abort = Some(postgres_abort(&e));
+                            recorder.end_attempt(transaction, start);
                             continue;
                         }
                     };
@@ -68,6 +68,7 @@ impl ClusterNode for PostgresNode {
                             Err(e) => {
                                 // If an operation fails, then the whole transaction fails
                                 transaction.success = false;
+                                transaction.abort = Some(postgres_abort(&e));
                                 // eprintln!("WRITE ERR -- {:?}", e);
                                 break;
                             }
@@ -78,13 +79,16 @@ impl ClusterNode for PostgresNode {
                             &[&(event.variable as i64)],
                         ) {
                             Ok(result) => {
-                                let row = result.get(0);
-                                let value: i64 = row.unwrap().get("val");
+                                let row = result.first().ok_or_else(|| {
+                                    DbcopError::Execution(format!("variable {} does not exist", event.variable))
+                                })?;
+                                let value: i64 = row.get("val");
                                 event.value = value as usize;
                                 event.success = true;
                             }
                             Err(e) => {
                                 transaction.success = false;
+                                transaction.abort = Some(postgres_abort(&e));
                                 // eprintln!("READ ERR -- {:?}", e);
                                 break;
                             }
@@ -92,15 +96,18 @@ impl ClusterNode for PostgresNode {
                     }
                 }
 
-                transaction.success &= match sqltxn.commit() {
-                    Ok(_) => true,
-                    Err(e) => {
+                if transaction.success {
+                    if let Err(e) = sqltxn.commit() {
                         // eprintln!("COMMIT ERR -- {:?}", e);
-                        false
+                        transaction.success = false;
+                        transaction.abort = Some(postgres_abort(&e));
                     }
-                };
+                }
+                recorder.end_attempt(transaction, start);
             }
         }
+        recorder.finish(hist);
+        Ok(())
     }
 }
 
@@ -108,54 +115,58 @@ impl ClusterNode for PostgresNode {
 pub struct PostgresCluster(Vec<Node>, Arc<MultiProgress>);
 
 impl PostgresCluster {
-    pub fn new(ips: &Vec<&str>) -> Self {
-        PostgresCluster(PostgresCluster::node_vec(ips), Arc::new(MultiProgress::new()))
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        Ok(PostgresCluster(PostgresCluster::node_vec(ips)?, Arc::new(MultiProgress::new())))
     }
 
-    fn create_table(&self) -> bool {
-        match self.get_postgresql_addr(0) {
-            Some(ip) => Client::connect(ip.as_str(), NoTls)
-                .and_then(|mut pool| {
-                    pool.execute("CREATE SCHEMA IF NOT EXISTS dbcop",  &[]).unwrap();
-                    pool.execute("DROP TABLE IF EXISTS dbcop.variables",  &[]).unwrap();
-                    pool.batch_execute(
-                        "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT8 NOT NULL PRIMARY KEY, val INT8 NOT NULL) PARTITION BY HASH (var);
-                         CREATE TABLE IF NOT EXISTS dbcop.variables_0 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 0);
-                         CREATE TABLE IF NOT EXISTS dbcop.variables_1 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 1);
-                         CREATE TABLE IF NOT EXISTS dbcop.variables_2 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 2);"
-                    ).unwrap();
-                    // conn.query("USE dbcop").unwrap();
-                    Ok(true)
-                }).is_ok(),
-            _ => false,
-        }
+    fn connect(&self) -> Result<Client, DbcopError> {
+        let ip = self
+            .get_postgresql_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        Client::connect(ip.as_str(), NoTls).map_err(|e| DbcopError::Connection(e.to_string()))
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        if let Some(ip) = self.get_postgresql_addr(0) {
-            if let Ok(mut conn) = Client::connect(ip.as_str(), NoTls) {
-                let mut writer = conn.copy_in("COPY dbcop.variables FROM STDIN").unwrap();
-                (0..n_variable).for_each(|var| writer.write_all(format!("{}\t{}\n", var, 0).as_bytes()).unwrap());
-                writer.finish().unwrap();
-                // for stmt in conn
-                    // .prepare("INSERT INTO dbcop.variables (var, val) values ($1, 0)")
-                    // .into_iter()
-                // {
-                    // (0..n_variable).for_each(|variable| {
-                        // conn.execute(&stmt, &[&(variable as i64)])
-                            // .expect("Cannot create variable");
-                    // });
-                // }
-            }
-        }
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: postgres::Error| DbcopError::Setup(e.to_string());
+        let mut pool = self.connect()?;
+        pool.execute("CREATE SCHEMA IF NOT EXISTS dbcop",  &[]).map_err(setup)?;
+        pool.execute("DROP TABLE IF EXISTS dbcop.variables",  &[]).map_err(setup)?;
+        pool.batch_execute(
+            "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT8 NOT NULL PRIMARY KEY, val INT8 NOT NULL) PARTITION BY HASH (var);
+             CREATE TABLE IF NOT EXISTS dbcop.variables_0 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 0);
+             CREATE TABLE IF NOT EXISTS dbcop.variables_1 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 1);
+             CREATE TABLE IF NOT EXISTS dbcop.variables_2 PARTITION OF dbcop.variables FOR VALUES WITH (modulus 3, remainder 2);"
+        ).map_err(setup)?;
+        Ok(())
     }
 
-    fn drop_database(&self) {
-        if let Some(ip) = self.get_postgresql_addr(0) {
-            if let Ok(mut conn) = Client::connect(ip.as_str(), NoTls) {
-                conn.execute("DROP SCHEMA dbcop CASCADE", &[]).unwrap();
-            }
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let setup = |e: postgres::Error| DbcopError::Setup(e.to_string());
+        let mut conn = self.connect()?;
+        let mut writer = conn.copy_in("COPY dbcop.variables FROM STDIN").map_err(setup)?;
+        for var in 0..n_variable {
+            writer
+                .write_all(format!("{}\t{}\n", var, 0).as_bytes())
+                .map_err(|e| DbcopError::Setup(e.to_string()))?;
         }
+        writer.finish().map_err(setup)?;
+        // for stmt in conn
+            // .prepare("INSERT INTO dbcop.variables (var, val) values ($1, 0)")
+            // .into_iter()
+        // {
+            // (0..n_variable).for_each(|variable| {
+                // conn.execute(&stmt, &[&(variable as i64)])
+                    // .expect("Cannot create variable");
+            // });
+        // }
+        Ok(())
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.execute("DROP SCHEMA dbcop CASCADE", &[])
+            .map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
     }
 
     fn get_postgresql_addr(&self, i: usize) -> Option<String> {
@@ -173,7 +184,7 @@ impl Cluster<PostgresNode> for PostgresCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
@@ -182,17 +193,18 @@ impl Cluster<PostgresNode> for PostgresCluster {
     fn get_cluster_node(&self, id: usize) -> PostgresNode {
         PostgresNode::new(self.get_node(id), self)
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())?;
 
         let progress = self.1.clone();
         spawn(move || progress.join());
+        Ok(())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
-        "PostgreSQL".to_string()
+        "PostgreSQL (serializable)".to_string()
     }
 }
 
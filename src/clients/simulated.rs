@@ -0,0 +1,340 @@
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use clap::ValueEnum;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::db::cluster::{Cluster, ClusterNode, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{Abort, AbortKind, HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
+
+/// Isolation level of the simulated database, including deliberately broken ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SimulatedIsolation {
+    /// Every read sees the latest committed version.
+    ReadCommitted,
+    /// Transactions read a possibly stale snapshot that includes the session's own writes.
+    ReadAtomic,
+    /// Transactions read a possibly stale snapshot that includes everything the session saw.
+    Causal,
+    /// Transactions read the latest snapshot and the first committer of a variable wins.
+    SnapshotIsolation,
+    /// Snapshot isolation that also aborts transactions whose reads were overwritten.
+    Serializable,
+    /// Broken snapshot isolation that lets concurrent writers overwrite each other.
+    LostUpdate,
+    /// Broken snapshot isolation whose reads see the latest committed version.
+    NonRepeatableSnapshot,
+}
+
+impl SimulatedIsolation {
+    fn name(&self) -> &'static str {
+        match self {
+            SimulatedIsolation::ReadCommitted => "read committed",
+            SimulatedIsolation::ReadAtomic => "read atomic",
+            SimulatedIsolation::Causal => "causal",
+            SimulatedIsolation::SnapshotIsolation => "snapshot isolation",
+            SimulatedIsolation::Serializable => "serializable",
+            SimulatedIsolation::LostUpdate => "lost update",
+            SimulatedIsolation::NonRepeatableSnapshot => "non-repeatable snapshot",
+        }
+    }
+}
+
+/// Committed versions of every variable, ordered by the logical time they committed at.
+#[derive(Debug, Default)]
+struct Store {
+    versions: HashMap<usize, Vec<(u64, usize)>>,
+    clock: u64,
+}
+
+impl Store {
+    /// The value of `variable` in the snapshot of the commits up to `snapshot`.
+    fn read(&self, variable: usize, snapshot: u64) -> usize {
+        self.versions
+            .get(&variable)
+            .and_then(|versions| versions.iter().rev().find(|(time, _)| *time <= snapshot))
+            .map_or(0, |(_, value)| *value)
+    }
+
+    /// Whether `variable` was committed after `snapshot`.
+    fn changed_since(&self, variable: usize, snapshot: u64) -> bool {
+        self.versions
+            .get(&variable)
+            .and_then(|versions| versions.last())
+            .is_some_and(|(time, _)| *time > snapshot)
+    }
+
+    fn commit(&mut self, writes: &HashMap<usize, usize>) -> u64 {
+        self.clock += 1;
+        for (&variable, &value) in writes.iter() {
+            self.versions.entry(variable).or_default().push((self.clock, value));
+        }
+        self.clock
+    }
+}
+
+fn lock(store: &Mutex<Store>) -> Result<MutexGuard<'_, Store>, DbcopError> {
+    store.lock().map_err(|e| DbcopError::Execution(e.to_string()))
+}
+
+fn abort(kind: AbortKind, message: String) -> Abort {
+    Abort { kind, code: None, message }
+}
+
+#[derive(Debug)]
+struct Schedule {
+    rng: ChaCha8Rng,
+    seed: u64,
+    next_session: usize,
+    /// Sessions that have not finished.
+    active: usize,
+    /// Sessions waiting for their turn.
+    waiting: BTreeSet<usize>,
+    running: Option<usize>,
+}
+
+impl Schedule {
+    fn new(seed: u64) -> Self {
+        Schedule {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            seed,
+            next_session: 0,
+            active: 0,
+            waiting: BTreeSet::new(),
+            running: None,
+        }
+    }
+
+    /// Hands the turn to a session drawn at random once every active session waits for it.
+    fn dispatch(&mut self) {
+        if self.running.is_none() && self.active > 0 && self.waiting.len() == self.active {
+            let i = self.rng.gen_range(0..self.waiting.len());
+            self.running = self.waiting.iter().nth(i).copied();
+        }
+    }
+}
+
+/// Runs one session at a time, each step until the session asks for its next turn, in an order
+/// drawn from a seeded generator. The nodes of all sessions are created before any of them
+/// starts, so an execution only depends on the seed and not on the scheduling of the threads.
+#[derive(Debug)]
+struct Scheduler {
+    schedule: Mutex<Schedule>,
+    turn: Condvar,
+}
+
+impl Scheduler {
+    fn lock(&self) -> MutexGuard<'_, Schedule> {
+        // a session failing while holding the lock must not block the other ones
+        self.schedule.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn reset(&self, seed: u64) {
+        *self.lock() = Schedule::new(seed);
+    }
+
+    /// Registers a session, returning its id and the seed of its own generator.
+    fn register(&self) -> (usize, u64) {
+        let mut schedule = self.lock();
+        let session = schedule.next_session;
+        schedule.next_session += 1;
+        schedule.active += 1;
+        (session, schedule.seed.wrapping_add(session as u64))
+    }
+
+    /// Ends the step of `session` and waits for its next turn.
+    fn step(&self, session: usize) {
+        let mut schedule = self.lock();
+        if schedule.running == Some(session) {
+            schedule.running = None;
+        }
+        schedule.waiting.insert(session);
+        schedule.dispatch();
+        self.turn.notify_all();
+        while schedule.running != Some(session) {
+            schedule = self.turn.wait(schedule).unwrap_or_else(PoisonError::into_inner);
+        }
+        schedule.waiting.remove(&session);
+    }
+
+    fn finish(&self, session: usize) {
+        let mut schedule = self.lock();
+        if schedule.running == Some(session) {
+            schedule.running = None;
+        }
+        schedule.active -= 1;
+        schedule.dispatch();
+        self.turn.notify_all();
+    }
+}
+
+#[derive(Debug)]
+pub struct SimulatedNode {
+    store: Arc<Mutex<Store>>,
+    scheduler: Arc<Scheduler>,
+    session: usize,
+    seed: u64,
+    isolation: SimulatedIsolation,
+}
+
+impl Drop for SimulatedNode {
+    fn drop(&mut self) {
+        // also lets the other sessions go on if this one never ran
+        self.scheduler.finish(self.session);
+    }
+}
+
+impl ClusterNode for SimulatedNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        // commits the session must see, its own ones and, for causal, everything it read from
+        let mut floor = 0;
+
+        let mut recorder = SessionRecorder::new(retry);
+        for transaction in hist.iter_mut() {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
+                transaction.success = true;
+
+                self.scheduler.step(self.session);
+                let snapshot = {
+                    let store = lock(&self.store)?;
+                    match self.isolation {
+                        SimulatedIsolation::ReadAtomic | SimulatedIsolation::Causal => rng.gen_range(floor..=store.clock),
+                        _ => store.clock,
+                    }
+                };
+                if self.isolation == SimulatedIsolation::Causal {
+                    floor = snapshot;
+                }
+
+                let mut writes = HashMap::new();
+                let mut reads = Vec::new();
+                for event in transaction.events.iter_mut() {
+                    // let other sessions interleave
+                    self.scheduler.step(self.session);
+                    if event.write {
+                        writes.insert(event.variable, event.value);
+                    } else if let Some(&value) = writes.get(&event.variable) {
+                        event.value = value;
+                    } else {
+                        let store = lock(&self.store)?;
+                        event.value = match self.isolation {
+                            SimulatedIsolation::ReadCommitted | SimulatedIsolation::NonRepeatableSnapshot => {
+                                store.read(event.variable, store.clock)
+                            }
+                            _ => store.read(event.variable, snapshot),
+                        };
+                        reads.push(event.variable);
+                    }
+                    event.success = true;
+                }
+
+                self.scheduler.step(self.session);
+                let mut store = lock(&self.store)?;
+                let first_committer_wins = matches!(
+                    self.isolation,
+                    SimulatedIsolation::SnapshotIsolation
+                        | SimulatedIsolation::Serializable
+                        | SimulatedIsolation::NonRepeatableSnapshot
+                );
+                let conflict = writes
+                    .keys()
+                    .find(|&&variable| first_committer_wins && store.changed_since(variable, snapshot));
+                let stale_read = reads.iter().find(|&&variable| {
+                    self.isolation == SimulatedIsolation::Serializable && store.changed_since(variable, snapshot)
+                });
+                if let Some(variable) = conflict {
+                    transaction.success = false;
+                    transaction.abort = Some(abort(
+                        AbortKind::CommitConflict,
+                        format!("variable {} was written by a concurrent transaction", variable),
+                    ));
+                } else if let Some(variable) = stale_read {
+                    transaction.success = false;
+                    transaction.abort = Some(abort(
+                        AbortKind::SerializationFailure,
+                        format!("variable {} was overwritten after it was read", variable),
+                    ));
+                } else if !writes.is_empty() {
+                    floor = floor.max(store.commit(&writes));
+                }
+                drop(store);
+                recorder.end_attempt(transaction, start);
+            }
+        }
+        recorder.finish(hist);
+        Ok(())
+    }
+}
+
+/// An in-memory multi-version key-value store, shared by all its nodes. The sessions are
+/// interleaved at random, seeded by the seed of the history.
+#[derive(Debug)]
+pub struct SimulatedCluster {
+    nodes: Vec<Node>,
+    store: Arc<Mutex<Store>>,
+    scheduler: Arc<Scheduler>,
+    isolation: SimulatedIsolation,
+}
+
+impl SimulatedCluster {
+    pub fn new(n_node: usize, isolation: SimulatedIsolation) -> Result<Self, DbcopError> {
+        if n_node == 0 {
+            return Err(DbcopError::Setup("the cluster has no node".to_string()));
+        }
+        let nodes = (0..n_node)
+            .map(|i| Node {
+                addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+                id: i + 1,
+            })
+            .collect();
+        Ok(SimulatedCluster {
+            nodes,
+            store: Arc::new(Mutex::new(Store::default())),
+            scheduler: Arc::new(Scheduler {
+                schedule: Mutex::new(Schedule::new(0)),
+                turn: Condvar::new(),
+            }),
+            isolation,
+        })
+    }
+}
+
+impl Cluster<SimulatedNode> for SimulatedCluster {
+    fn n_node(&self) -> usize {
+        self.nodes.len()
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        Ok(())
+    }
+    fn get_node(&self, id: usize) -> Node {
+        self.nodes[id].clone()
+    }
+    fn get_cluster_node(&self, _id: usize) -> SimulatedNode {
+        let (session, seed) = self.scheduler.register();
+        SimulatedNode {
+            store: self.store.clone(),
+            scheduler: self.scheduler.clone(),
+            session,
+            seed,
+            isolation: self.isolation,
+        }
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        // variables not written yet read as 0
+        self.scheduler.reset(p.get_seed().unwrap_or(0));
+        Ok(())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        *lock(&self.store)? = Store::default();
+        Ok(())
+    }
+    fn info(&self) -> String {
+        format!("Simulated ({})", self.isolation.name())
+    }
+}
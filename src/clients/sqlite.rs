@@ -0,0 +1,192 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::ValueEnum;
+use rusqlite::{params, Connection, TransactionBehavior};
+
+use crate::clients::sqlite_abort;
+use crate::db::cluster::{Cluster, ClusterNode, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
+
+/// How long a connection waits for a lock held by another one before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// When a transaction takes the write lock of the database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SqliteBegin {
+    /// At its first write, failing if another transaction committed since its first read.
+    Deferred,
+    /// When it begins.
+    Immediate,
+}
+
+impl From<SqliteBegin> for TransactionBehavior {
+    fn from(begin: SqliteBegin) -> Self {
+        match begin {
+            SqliteBegin::Deferred => TransactionBehavior::Deferred,
+            SqliteBegin::Immediate => TransactionBehavior::Immediate,
+        }
+    }
+}
+
+fn connect(path: &Path) -> Result<Connection, DbcopError> {
+    let conn = Connection::open(path).map_err(|e| DbcopError::Connection(format!("{:?}: {}", path, e)))?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| DbcopError::Connection(e.to_string()))?;
+    Ok(conn)
+}
+
+#[derive(Debug)]
+pub struct SqliteNode {
+    path: PathBuf,
+    begin: SqliteBegin,
+}
+
+impl ClusterNode for SqliteNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let mut conn = connect(&self.path)?;
+
+        let mut recorder = SessionRecorder::new(retry);
+        for transaction in hist.iter_mut() {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
+                transaction.success = true;
+                let sqltxn = match conn.transaction_with_behavior(self.begin.into()) {
+                    Ok(sqltxn) => sqltxn,
+                    Err(e) => {
+                        transaction.success = false;
+                        transaction.abort = Some(sqlite_abort(&e));
+                        recorder.end_attempt(transaction, start);
+                        continue;
+                    }
+                };
+
+                for event in transaction.events.iter_mut() {
+                    let result = if event.write {
+                        sqltxn
+                            .execute(
+                                "UPDATE variables SET val=?1 WHERE var=?2",
+                                params![event.value as i64, event.variable as i64],
+                            )
+                            .map(|_| ())
+                    } else {
+                        sqltxn
+                            .query_row(
+                                "SELECT val FROM variables WHERE var=?1",
+                                params![event.variable as i64],
+                                |row| row.get::<_, i64>(0),
+                            )
+                            .map(|value| event.value = value as usize)
+                    };
+                    match result {
+                        Ok(()) => event.success = true,
+                        Err(e) => {
+                            transaction.success = false;
+                            transaction.abort = Some(sqlite_abort(&e));
+                            break;
+                        }
+                    }
+                }
+
+                // a failed transaction is rolled back when dropped
+                if transaction.success {
+                    if let Err(e) = sqltxn.commit() {
+                        transaction.success = false;
+                        transaction.abort = Some(sqlite_abort(&e));
+                    }
+                }
+                recorder.end_attempt(transaction, start);
+            }
+        }
+        recorder.finish(hist);
+        Ok(())
+    }
+}
+
+/// A SQLite database file, every session on its own connection to it.
+#[derive(Debug)]
+pub struct SqliteCluster {
+    node: Node,
+    path: PathBuf,
+    begin: SqliteBegin,
+}
+
+impl SqliteCluster {
+    pub fn new(path: &Path, begin: SqliteBegin) -> Result<Self, DbcopError> {
+        Ok(SqliteCluster {
+            node: Node {
+                addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+                id: 1,
+            },
+            path: path.to_path_buf(),
+            begin,
+        })
+    }
+
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: rusqlite::Error| DbcopError::Setup(e.to_string());
+        let conn = connect(&self.path)?;
+
+        // readers do not block the writer
+        conn.pragma_update(None, "journal_mode", "WAL").map_err(setup)?;
+        conn.execute("DROP TABLE IF EXISTS variables", []).map_err(setup)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS variables (var INTEGER NOT NULL PRIMARY KEY, val INTEGER NOT NULL)",
+            [],
+        ).map_err(setup)?;
+        Ok(())
+    }
+
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let setup = |e: rusqlite::Error| DbcopError::Setup(e.to_string());
+        let mut conn = connect(&self.path)?;
+
+        let sqltxn = conn.transaction().map_err(setup)?;
+        {
+            let mut stmt = sqltxn.prepare("INSERT INTO variables (var, val) VALUES (?1, 0)").map_err(setup)?;
+            for variable in 0..n_variable {
+                stmt.execute(params![variable as i64]).map_err(setup)?;
+            }
+        }
+        sqltxn.commit().map_err(setup)
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let conn = connect(&self.path)?;
+
+        conn.execute("DROP TABLE variables", []).map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
+    }
+}
+
+impl Cluster<SqliteNode> for SqliteCluster {
+    fn n_node(&self) -> usize {
+        1
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        self.create_table()
+    }
+    fn get_node(&self, _id: usize) -> Node {
+        self.node.clone()
+    }
+    fn get_cluster_node(&self, _id: usize) -> SqliteNode {
+        SqliteNode {
+            path: self.path.clone(),
+            begin: self.begin,
+        }
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
+    }
+    fn info(&self) -> String {
+        match self.begin {
+            SqliteBegin::Deferred => "SQLite".to_string(),
+            SqliteBegin::Immediate => "SQLite (immediate)".to_string(),
+        }
+    }
+}
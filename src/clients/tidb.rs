@@ -1,141 +1,170 @@
 use std::path::Path;
 
+use crate::clients::mysql_abort;
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::error::DbcopError;
+use crate::db::history::{HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
 
 use std::fs;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ValueEnum};
 
 use mysql::{AccessMode, Conn, IsolationLevel, Opts, TxOpts, prelude::Queryable};
 
+/// How TiDB handles conflicting writes of concurrent transactions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TiDBTxnMode {
+    /// The conflict is detected when the later transaction commits, aborting it.
+    Optimistic,
+    /// The later write waits for the lock of the earlier one.
+    Pessimistic,
+}
+
+impl TiDBTxnMode {
+    fn name(&self) -> &'static str {
+        match self {
+            TiDBTxnMode::Optimistic => "optimistic",
+            TiDBTxnMode::Pessimistic => "pessimistic",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TiDBNode {
     addr: String,
     id: usize,
+    txn_mode: TiDBTxnMode,
 }
 
-impl From<Node> for TiDBNode {
-    fn from(node: Node) -> Self {
+impl TiDBNode {
+    fn new(node: Node, txn_mode: TiDBTxnMode) -> Self {
         TiDBNode {
             addr: format!("mysql://{}@{}", "root", node.addr),
             id: node.id,
+            txn_mode,
         }
     }
 }
 
 impl ClusterNode for TiDBNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
-        match Conn::new(Opts::from_url(&self.addr).unwrap()) {
-            Ok(mut conn) => hist.iter_mut().for_each(|transaction| {
-                for mut sqltxn in conn.start_transaction(
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let opts = Opts::from_url(&self.addr).map_err(|e| DbcopError::Connection(e.to_string()))?;
+        let mut conn = Conn::new(opts).map_err(|e| DbcopError::Connection(format!("node {}: {}", self.id, e)))?;
+        conn.query_drop(format!("SET SESSION tidb_txn_mode = '{}'", self.txn_mode.name()))
+            .map_err(|e| DbcopError::Connection(format!("node {}: {}", self.id, e)))?;
+
+        let mut recorder = SessionRecorder::new(retry);
+        for transaction in hist.iter_mut() {
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
+                let mut sqltxn = match conn.start_transaction(
                     TxOpts::default()
                         .set_with_consistent_snapshot(true)
                         .set_isolation_level(Some(IsolationLevel::RepeatableRead))
                         .set_access_mode(Some(AccessMode::ReadWrite)),
                 ) {
-                    transaction.events.iter_mut().for_each(|event| {
-                        if event.write {
-                            if let Err(_e) = sqltxn.exec_drop(
-                                "UPDATE dbcop.variables SET val=? WHERE var=?",
-                                (event.value, event.variable),
-                            ) {
-                                assert_eq!(event.success, false);
-                                // println!("WRITE ERR -- {:?}", _e);
-                            } else {
-                                event.success = true
+                    Ok(sqltxn) => sqltxn,
+                    Err(e) => {
+                        transaction.success = false;
+                        transaction.abort = Some(mysql_abort(&e));
+                        recorder.end_attempt(transaction, start);
+                        continue;
+                    }
+                };
+                transaction.success = true;
+
+                for event in transaction.events.iter_mut() {
+                    if event.write {
+                        if let Err(e) = sqltxn.exec_drop(
+                            "UPDATE dbcop.variables SET val=? WHERE var=?",
+                            (event.value, event.variable),
+                        ) {
+                            // a failed statement rolls the transaction back
+                            transaction.success = false;
+                            transaction.abort = Some(mysql_abort(&e));
+                            break;
+                        }
+                        event.success = true;
+                    } else {
+                        match sqltxn.exec_first("SELECT * FROM dbcop.variables WHERE var=?", (event.variable,)) {
+                            Ok(result) => {
+                                let mut row: mysql::Row = result.ok_or_else(|| {
+                                    DbcopError::Execution(format!("variable {} does not exist", event.variable))
+                                })?;
+                                event.value = row.take("val").ok_or_else(|| {
+                                    DbcopError::Execution(format!("variable {} has no value", event.variable))
+                                })?;
+                                event.success = true;
                             }
-                        } else {
-                            match sqltxn.exec_iter(
-                                "SELECT * FROM dbcop.variables WHERE var=?",
-                                (event.variable,),
-                            ) {
-                                Ok(mut result) => {
-                                    if let Some(q_result) = result.next() {
-                                        let mut row = q_result.unwrap();
-                                        if let Some(value) = row.take("val") {
-                                            event.value = value;
-                                            event.success = true;
-                                        }
-                                    } else {
-                                        // may be diverged
-                                        assert_eq!(event.success, false);
-                                    }
-                                }
-                                Err(_e) => {
-                                    // println!("READ ERR -- {:?}", _e);
-                                    assert_eq!(event.success, false);
-                                }
+                            Err(e) => {
+                                transaction.success = false;
+                                transaction.abort = Some(mysql_abort(&e));
+                                break;
                             }
                         }
-                    });
-                    match sqltxn.commit() {
-                        Ok(_) => {
-                            transaction.success = true;
-                        }
-                        Err(_e) => {
-                            assert_eq!(transaction.success, false);
-                            // println!("{:?} -- COMMIT ERROR {}", transaction, _e);
-                        }
                     }
                 }
-            }),
-            Err(_e) => {
-                hist.iter().for_each(|transaction| {
-                    assert_eq!(transaction.success, false);
-                });
-                // println!("CONNECTION ERROR {}", _e);}
+
+                if transaction.success {
+                    if let Err(e) = sqltxn.commit() {
+                        transaction.success = false;
+                        transaction.abort = Some(mysql_abort(&e));
+                    }
+                }
+                recorder.end_attempt(transaction, start);
             }
         }
+        recorder.finish(hist);
+        Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct TiDBCluster(Vec<Node>);
+pub struct TiDBCluster(Vec<Node>, TiDBTxnMode);
 
 impl TiDBCluster {
-    fn new(ips: &Vec<&str>) -> Self {
-        TiDBCluster(TiDBCluster::node_vec(ips))
-    }
-
-    fn create_table(&self) -> bool {
-        match self.get_mysql_addr(0) {
-            Some(ip) => Conn::new(Opts::from_url(ip.as_str()).unwrap())
-                .and_then(|mut pool| {
-                    pool.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).unwrap();
-                    pool.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).unwrap();
-                    pool.exec_drop(
-                        "CREATE TABLE IF NOT EXISTS dbcop.variables (var BIGINT(64) UNSIGNED NOT NULL PRIMARY KEY, val BIGINT(64) UNSIGNED NOT NULL)", ()
-                    ).unwrap();
-                    pool.exec_drop("SET GLOBAL tidb_txn_mode = 'optimistic'", ()).unwrap();
-                    // conn.query("USE dbcop").unwrap();
-                    Ok(true)
-                }).expect("problem creating database"),
-            _ => false,
-        }
+    pub fn new(ips: &Vec<&str>, txn_mode: TiDBTxnMode) -> Result<Self, DbcopError> {
+        Ok(TiDBCluster(TiDBCluster::node_vec(ips)?, txn_mode))
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        if let Some(ip) = self.get_mysql_addr(0) {
-            if let Ok(mut conn) = Conn::new(Opts::from_url(ip.as_str()).unwrap()) {
-                for stmt in conn
-                    .prep("INSERT INTO dbcop.variables (var, val) values (?, 0)")
-                    .into_iter()
-                {
-                    (0..n_variable).for_each(|variable| {
-                        conn.exec_drop(&stmt, (variable,)).unwrap();
-                    });
-                }
-            }
-        }
+    fn connect(&self) -> Result<Conn, DbcopError> {
+        let ip = self
+            .get_mysql_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        Opts::from_url(ip.as_str())
+            .map_err(|e| DbcopError::Connection(e.to_string()))
+            .and_then(|opts| Conn::new(opts).map_err(|e| DbcopError::Connection(e.to_string())))
     }
 
-    fn drop_database(&self) {
-        if let Some(ip) = self.get_mysql_addr(0) {
-            if let Ok(mut conn) = Conn::new(Opts::from_url(ip.as_str()).unwrap()) {
-                conn.exec_drop("DROP DATABASE dbcop", ()).unwrap();
-            }
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: mysql::Error| DbcopError::Setup(e.to_string());
+        let mut pool = self.connect()?;
+        pool.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).map_err(setup)?;
+        pool.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).map_err(setup)?;
+        pool.exec_drop(
+            "CREATE TABLE IF NOT EXISTS dbcop.variables (var BIGINT(64) UNSIGNED NOT NULL PRIMARY KEY, val BIGINT(64) UNSIGNED NOT NULL)", ()
+        ).map_err(setup)?;
+        // conn.query("USE dbcop").unwrap();
+        Ok(())
+    }
+
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let setup = |e: mysql::Error| DbcopError::Setup(e.to_string());
+        let mut conn = self.connect()?;
+        let stmt = conn
+            .prep("INSERT INTO dbcop.variables (var, val) values (?, 0)")
+            .map_err(setup)?;
+        for variable in 0..n_variable {
+            conn.exec_drop(&stmt, (variable,)).map_err(setup)?;
         }
+        Ok(())
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.exec_drop("DROP DATABASE dbcop", ())
+            .map_err(|e| DbcopError::Setup(e.to_string()))
     }
 
     fn get_mysql_addr(&self, i: usize) -> Option<String> {
@@ -150,23 +179,23 @@ impl Cluster<TiDBNode> for TiDBCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
         self.0[id].clone()
     }
     fn get_cluster_node(&self, id: usize) -> TiDBNode {
-        From::from(self.get_node(id))
+        TiDBNode::new(self.get_node(id), self.1)
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
-        "Galera".to_string()
+        format!("TiDB ({})", self.1.name())
     }
 }
 
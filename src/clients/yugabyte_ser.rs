@@ -1,8 +1,11 @@
 use std::fs;
 use std::path::Path;
 
+use crate::clients::postgres_abort;
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::error::DbcopError;
+use crate::db::history::{HistParams, SessionRecorder, Timestamp, Transaction};
+use crate::db::retry::RetryPolicy;
 
 use clap::{App, Arg};
 
@@ -24,72 +27,78 @@ impl From<Node> for YugabyteNode {
 }
 
 impl ClusterNode for YugabyteNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
-        let mut conn = match Client::connect(self.addr.as_str(), NoTls) {
-            Ok(conn) => conn,
-            Err(e) => {
-                assert_eq!(false, hist.iter().fold(false, |b, t| b | t.success));
-                println!("CONNECTION ERROR {}", e);
-                return;
-            }
-        };
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy) -> Result<(), DbcopError> {
+        let mut conn = Client::connect(self.addr.as_str(), NoTls)
+            .map_err(|e| DbcopError::Connection(format!("node {}: {}", self.id, e)))?;
 
+        let mut recorder = SessionRecorder::new(retry);
         for transaction in hist.iter_mut() {
-            transaction.success = true;
-            let mut sqltxn = match conn
-                .build_transaction()
-                .isolation_level(postgres::IsolationLevel::Serializable)
-                .start()
-            {
-                Ok(txn) => txn,
-                Err(e) => {
-                    println!("{:?} - TRANSACTION ERROR", e);
-                    transaction.success = false;
-                    continue;
-                }
-            };
-
-            for event in transaction.events.iter_mut() {
-                if event.write {
-                    match sqltxn.execute(
-                        "UPDATE dbcop.variables SET val=$1 WHERE var=$2",
-                        &[&(event.value as i64), &(event.variable as i64)],
-                    ) {
-                        Ok(_) => event.success = true,
-                        Err(e) => {
-                            // If an operation fails, then the whole transaction fails
-                            transaction.success = false;
-                            // println!("WRITE ERR -- {:?}", e);
-                            break;
-                        }
+            while recorder.next_attempt(transaction) {
+                transaction.success = true;
+                let start = Timestamp::now();
+                let mut sqltxn = match conn
+                    .build_transaction()
+                    .isolation_level(postgres::IsolationLevel::Serializable)
+                    .start()
+                {
+                    Ok(txn) => txn,
+                    Err(e) => {
+                        transaction.success = false;
+                        transaction.abort = Some(postgres_abort(&e));
+                        recorder.end_attempt(transaction, start);
+                        continue;
                     }
-                } else {
-                    match sqltxn.query(
-                        "SELECT * FROM dbcop.variables WHERE var=$1",
-                        &[&(event.variable as i64)],
-                    ) {
-                        Ok(result) => {
-                            let row = result.get(0);
-                            let value: i64 = row.unwrap().get("val");
-                            event.value = value as usize;
-                            event.success = true;
+                };
+
+                for event in transaction.events.iter_mut() {
+                    if event.write {
+                        match sqltxn.execute(
+                            "UPDATE dbcop.variables SET val=$1 WHERE var=$2",
+                            &[&(event.value as i64), &(event.variable as i64)],
+                        ) {
+                            Ok(_) => event.success = true,
+                            Err(e) => {
+                                // If an operation fails, then the whole transaction fails
+                                transaction.success = false;
+                                transaction.abort = Some(postgres_abort(&e));
+                                // println!("WRITE ERR -- {:?}", e);
+                                break;
+                            }
                         }
-                        Err(e) => {
-                            transaction.success = false;
-                            // println!("READ ERR -- {:?}", e);
-                            break;
+                    } else {
+                        match sqltxn.query(
+                            "SELECT * FROM dbcop.variables WHERE var=$1",
+                            &[&(event.variable as i64)],
+                        ) {
+                            Ok(result) => {
+                                let row = result.first().ok_or_else(|| {
+                                    DbcopError::Execution(format!("variable {} does not exist", event.variable))
+                                })?;
+                                let value: i64 = row.get("val");
+                                event.value = value as usize;
+                                event.success = true;
+                            }
+                            Err(e) => {
+                                transaction.success = false;
+                                transaction.abort = Some(postgres_abort(&e));
+                                // println!("READ ERR -- {:?}", e);
+                                break;
+                            }
                         }
                     }
                 }
-            }
 
-            transaction.success &= if let Err(e) = sqltxn.commit() {
-                println!("{:?} -- COMMIT ERROR {}", transaction, e);
-                false
-            } else {
-                true
-            };
+                if transaction.success {
+                    if let Err(e) = sqltxn.commit() {
+                        transaction.success = false;
+                        transaction.abort = Some(postgres_abort(&e));
+                    }
+                }
+                recorder.end_attempt(transaction, start);
+            }
         }
+        recorder.finish(hist);
+        Ok(())
     }
 }
 
@@ -97,48 +106,45 @@ impl ClusterNode for YugabyteNode {
 pub struct YugabyteCluster(Vec<Node>);
 
 impl YugabyteCluster {
-    fn new(ips: &Vec<&str>) -> Self {
-        YugabyteCluster(YugabyteCluster::node_vec(ips))
+    pub fn new(ips: &Vec<&str>) -> Result<Self, DbcopError> {
+        Ok(YugabyteCluster(YugabyteCluster::node_vec(ips)?))
     }
 
-    fn create_table(&self) -> bool {
-        match self.get_postgresql_addr(0) {
-            Some(ip) => Client::connect(ip.as_str(), NoTls)
-                .and_then(|mut pool| {
-                    pool.execute("CREATE SCHEMA IF NOT EXISTS dbcop",  &[]).unwrap();
-                    pool.execute("DROP TABLE IF EXISTS dbcop.variables",  &[]).unwrap();
-                    pool.execute(
-                        "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT8 NOT NULL PRIMARY KEY, val INT8 NOT NULL)", &[]
-                    ).unwrap();
-                    // conn.query("USE dbcop").unwrap();
-                    Ok(true)
-                }).is_ok(),
-            _ => false,
-        }
+    fn connect(&self) -> Result<Client, DbcopError> {
+        let ip = self
+            .get_postgresql_addr(0)
+            .ok_or_else(|| DbcopError::Connection("the cluster has no node".to_string()))?;
+        Client::connect(ip.as_str(), NoTls).map_err(|e| DbcopError::Connection(e.to_string()))
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        if let Some(ip) = self.get_postgresql_addr(0) {
-            if let Ok(mut conn) = Client::connect(ip.as_str(), NoTls) {
-                for stmt in conn
-                    .prepare("INSERT INTO dbcop.variables (var, val) values ($1, 0)")
-                    .into_iter()
-                {
-                    (0..n_variable).for_each(|variable| {
-                        conn.execute(&stmt, &[&(variable as i64)])
-                            .expect("Cannot create variable");
-                    });
-                }
-            }
-        }
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let setup = |e: postgres::Error| DbcopError::Setup(e.to_string());
+        let mut pool = self.connect()?;
+        pool.execute("CREATE SCHEMA IF NOT EXISTS dbcop",  &[]).map_err(setup)?;
+        pool.execute("DROP TABLE IF EXISTS dbcop.variables",  &[]).map_err(setup)?;
+        pool.execute(
+            "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT8 NOT NULL PRIMARY KEY, val INT8 NOT NULL)", &[]
+        ).map_err(setup)?;
+        Ok(())
     }
 
-    fn drop_database(&self) {
-        if let Some(ip) = self.get_postgresql_addr(0) {
-            if let Ok(mut conn) = Client::connect(ip.as_str(), NoTls) {
-                conn.execute("DROP SCHEMA dbcop CASCADE", &[]).unwrap();
-            }
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let setup = |e: postgres::Error| DbcopError::Setup(e.to_string());
+        let mut conn = self.connect()?;
+        let stmt = conn
+            .prepare("INSERT INTO dbcop.variables (var, val) values ($1, 0)")
+            .map_err(setup)?;
+        for variable in 0..n_variable {
+            conn.execute(&stmt, &[&(variable as i64)]).map_err(setup)?;
         }
+        Ok(())
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.execute("DROP SCHEMA dbcop CASCADE", &[])
+            .map_err(|e| DbcopError::Setup(e.to_string()))?;
+        Ok(())
     }
 
     fn get_postgresql_addr(&self, i: usize) -> Option<String> {
@@ -156,7 +162,7 @@ impl Cluster<YugabyteNode> for YugabyteCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
@@ -165,14 +171,14 @@ impl Cluster<YugabyteNode> for YugabyteCluster {
     fn get_cluster_node(&self, id: usize) -> YugabyteNode {
         From::from(self.get_node(id))
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
-        "YugabyteDB".to_string()
+        "YugabyteDB (serializable)".to_string()
     }
 }
 
@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::db::history::{AbortKind, Session};
+
+use super::graph::{DependencyGraph, Edge, EdgeKind};
+use super::util::{Source, TransactionalHistory, Variable};
+use super::{cycle_repr, Violation};
+
+/// Number of examples kept for each phenomenon.
+const MAX_EXAMPLES: usize = 5;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Phenomenon {
+    pub count: usize,
+    pub examples: Vec<String>,
+}
+
+impl Phenomenon {
+    fn record(&mut self, example: String) {
+        self.count += 1;
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(example);
+        }
+    }
+}
+
+/// Occurrences of Adya's phenomena in a history.
+///
+/// Cycle phenomena count distinct minimal cycles of the direct serialization graph, the
+/// read phenomena count offending reads. `other` collects reads that cannot be attributed
+/// to any write at all.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AnomalyReport {
+    pub committed: usize,
+    pub aborted: usize,
+    /// Aborted attempts by the reason they aborted for.
+    pub aborts: BTreeMap<AbortKind, usize>,
+    #[serde(rename = "G0")]
+    pub g0: Phenomenon,
+    #[serde(rename = "G1a")]
+    pub g1a: Phenomenon,
+    #[serde(rename = "G1b")]
+    pub g1b: Phenomenon,
+    #[serde(rename = "G1c")]
+    pub g1c: Phenomenon,
+    #[serde(rename = "G-single")]
+    pub g_single: Phenomenon,
+    #[serde(rename = "G2")]
+    pub g2: Phenomenon,
+    pub other: Phenomenon,
+    /// Committed writes of a variable the transaction did not read first. Their version order
+    /// is unknown, so no G0 cycle through them is reported.
+    pub blind_writes: usize,
+}
+
+/// Classifies a history into Adya's phenomena.
+///
+/// The version order of a variable is only known where a transaction reads the variable
+/// and then overwrites it, and the initial state precedes every write. Write-write and
+/// anti-dependency edges are inferred from that partial order alone, so the reported
+/// cycles are present under any version order the database could have chosen, and blind
+/// writes are never part of a G0 cycle. Two transactions that read the same version of a
+/// variable and both overwrite it form a G-single cycle under either order of their writes,
+/// and are reported as such.
+pub fn classify(histories: &[Session]) -> AnomalyReport {
+    let (history, anomalies) = TransactionalHistory::with_anomalies(histories);
+    let mut report = AnomalyReport {
+        committed: history.infos.len(),
+        aborted: histories.iter().flatten().filter(|txn| !txn.success).count(),
+        ..Default::default()
+    };
+
+    for txn in histories.iter().flatten().filter(|txn| !txn.success) {
+        let kind = txn.abort.as_ref().map_or(AbortKind::Other, |abort| abort.kind);
+        *report.aborts.entry(kind).or_default() += 1;
+    }
+
+    for anomaly in anomalies.iter() {
+        match anomaly {
+            Violation::AbortedRead { .. } => report.g1a.record(anomaly.to_string()),
+            Violation::IntermediateRead { .. } => report.g1b.record(anomaly.to_string()),
+            _ => report.other.record(anomaly.to_string()),
+        }
+    }
+
+    // known version order, as direct successors of each write
+    let mut overwrites: BTreeMap<(Source, Variable), BTreeSet<Source>> = BTreeMap::new();
+    for (&txn, info) in history.infos.iter() {
+        for read in info.reads.iter() {
+            if info.writes.contains(&read.variable) {
+                overwrites.entry((read.source, read.variable)).or_default().insert(Some(txn));
+            }
+        }
+        report.blind_writes += info
+            .writes
+            .iter()
+            .filter(|&&variable| !info.reads.iter().any(|read| read.variable == variable))
+            .count();
+    }
+
+    // concurrent overwriters of the same version, whichever is first is overwritten by the
+    // other one and read the version the other one overwrote
+    let mut seen = BTreeSet::new();
+    for (&(_, variable), successors) in overwrites.iter() {
+        let successors: Vec<Source> = successors.iter().copied().collect();
+        for (i, &first) in successors.iter().enumerate() {
+            for &second in successors[i + 1..].iter() {
+                let cycle = [
+                    Edge { from: first, to: second, kind: EdgeKind::WriteWrite { variable, reader: None } },
+                    Edge { from: second, to: first, kind: EdgeKind::ReadWrite(variable) },
+                ];
+                if seen.insert(vec![first, second]) {
+                    report.g_single.record(cycle_repr(&cycle));
+                }
+            }
+        }
+    }
+
+    let mut edges = Vec::new();
+    // the initial state has no incoming edges, so it is never part of a cycle
+    for (&(from, variable), successors) in overwrites.iter().filter(|((from, _), _)| from.is_some()) {
+        for &to in successors.iter() {
+            edges.push(Edge { from, to, kind: EdgeKind::WriteWrite { variable, reader: None } });
+        }
+    }
+    for (&txn, info) in history.infos.iter() {
+        for read in info.reads.iter() {
+            let variable = read.variable;
+            if read.source.is_some() {
+                edges.push(Edge { from: read.source, to: Some(txn), kind: EdgeKind::WriteRead(variable) });
+            }
+            let overwriters: Vec<Source> = match read.source {
+                None => history.writers.get(&variable).map_or(Vec::new(), |writers| {
+                    writers.iter().map(|&writer| Some(writer)).collect()
+                }),
+                source => overwrites
+                    .get(&(source, variable))
+                    .map_or(Vec::new(), |successors| successors.iter().copied().collect()),
+            };
+            for to in overwriters.into_iter().filter(|&to| to != Some(txn)) {
+                edges.push(Edge { from: Some(txn), to, kind: EdgeKind::ReadWrite(variable) });
+            }
+        }
+    }
+
+    let mut ww_graph = DependencyGraph::default();
+    let mut dependency_graph = DependencyGraph::default();
+    let mut full_graph = DependencyGraph::default();
+    for edge in edges.iter() {
+        if let EdgeKind::WriteWrite { .. } = edge.kind {
+            ww_graph.add_edge(edge.from, edge.to, edge.kind);
+        }
+        if let EdgeKind::WriteWrite { .. } | EdgeKind::WriteRead(_) = edge.kind {
+            dependency_graph.add_edge(edge.from, edge.to, edge.kind);
+        }
+        full_graph.add_edge(edge.from, edge.to, edge.kind);
+    }
+
+    for edge in edges.iter() {
+        let (phenomenon, graph) = match edge.kind {
+            EdgeKind::WriteWrite { .. } => (&mut report.g0, &ww_graph),
+            EdgeKind::WriteRead(_) => (&mut report.g1c, &dependency_graph),
+            EdgeKind::ReadWrite(_) => match dependency_graph.shortest_path(edge.to, edge.from) {
+                Some(_) => (&mut report.g_single, &dependency_graph),
+                None => (&mut report.g2, &full_graph),
+            },
+            EdgeKind::SessionOrder | EdgeKind::RealTime => unreachable!(),
+        };
+        if let Some(path) = graph.shortest_path(edge.to, edge.from) {
+            let mut cycle = vec![*edge];
+            cycle.extend(path);
+            // a cycle is found from each of its edges, and ww edges are visited first so
+            // that a cycle through the same transactions counts for the strongest phenomenon
+            let start = (0..cycle.len()).min_by_key(|&i| cycle[i].from).unwrap();
+            cycle.rotate_left(start);
+            if seen.insert(cycle.iter().map(|edge| edge.from).collect::<Vec<_>>()) {
+                phenomenon.record(cycle_repr(&cycle));
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::consistency::testing::{committed, read, transaction};
+    use crate::db::history::{Event, SessionRecorder, Timestamp};
+    use crate::db::retry::{Backoff, RetryPolicy};
+
+    #[test]
+    fn lost_update_is_g_single() {
+        let history = vec![
+            vec![committed(vec![read(0, 0), Event::write(0, 1)])],
+            vec![committed(vec![read(0, 0), Event::write(0, 2)])],
+        ];
+        let report = classify(&history);
+        assert_eq!(report.g_single.count, 1);
+        assert_eq!(report.g_single.examples, ["T(0,0) -ww(x0)-> T(1,0) -rw(x0)-> T(0,0)"]);
+        assert_eq!(report.g2.count, 0);
+        assert_eq!(report.blind_writes, 0);
+    }
+
+    #[test]
+    fn write_skew_is_g2() {
+        let history = vec![
+            vec![committed(vec![read(0, 0), Event::write(1, 1)])],
+            vec![committed(vec![read(1, 0), Event::write(0, 1)])],
+        ];
+        let report = classify(&history);
+        assert_eq!(report.g_single.count, 0);
+        assert_eq!(report.g2.count, 1);
+        assert_eq!(report.g2.examples, ["T(0,0) -rw(x0)-> T(1,0) -rw(x1)-> T(0,0)"]);
+        assert_eq!(report.blind_writes, 2);
+    }
+
+    #[test]
+    fn read_of_aborted_attempt_is_g1a() {
+        let retry = RetryPolicy {
+            max_attempts: None,
+            backoff: Backoff::Fixed,
+            delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        };
+
+        // the first attempt at writing x0 aborts, the second one commits
+        let mut writer = vec![transaction(vec![Event::write(0, 1)])];
+        let mut recorder = SessionRecorder::new(&retry);
+        let mut attempts = 0;
+        while recorder.next_attempt(&writer[0]) {
+            let start = Timestamp::now();
+            writer[0].events[0].success = true;
+            writer[0].success = attempts > 0;
+            attempts += 1;
+            recorder.end_attempt(&mut writer[0], start);
+        }
+        recorder.finish(&mut writer);
+        assert_eq!(writer.len(), 2);
+        assert_ne!(writer[0].events[0].value, writer[1].events[0].value);
+
+        // another session reads the value of the aborted attempt
+        let reader = committed(vec![read(0, writer[0].events[0].value)]);
+
+        let report = classify(&[writer, vec![reader]]);
+        assert_eq!(report.g1a.count, 1);
+        assert_eq!(report.g1a.examples, ["T(1,0) reads 1 from x0 written by aborted T(0,0)"]);
+        assert_eq!(report.committed, 2);
+        assert_eq!(report.aborted, 1);
+    }
+}
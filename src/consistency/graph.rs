@@ -0,0 +1,265 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use super::util::{Source, TransactionId, TransactionalHistory, Variable};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    SessionOrder,
+    WriteRead(Variable),
+    /// The write of `from` precedes the one of `to`. If the order is inferred, `reader`
+    /// reads the variable from `to` while `from` is visible to it.
+    WriteWrite {
+        variable: Variable,
+        reader: Option<TransactionId>,
+    },
+    ReadWrite(Variable),
+    /// `from` commits before `to` starts.
+    RealTime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Edge<K = EdgeKind> {
+    pub from: Source,
+    pub to: Source,
+    pub kind: K,
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EdgeKind::SessionOrder => write!(f, "so"),
+            EdgeKind::WriteRead(variable) => write!(f, "wr(x{})", variable),
+            EdgeKind::WriteWrite { variable, .. } => write!(f, "ww(x{})", variable),
+            EdgeKind::ReadWrite(variable) => write!(f, "rw(x{})", variable),
+            EdgeKind::RealTime => write!(f, "rt"),
+        }
+    }
+}
+
+/// Transactions of a history with labelled edges between them.
+///
+/// The initial state is the node `None` and precedes the first transaction of every session.
+#[derive(Clone, Debug)]
+pub struct DependencyGraph<K = EdgeKind> {
+    adjacency: HashMap<Source, Vec<(Source, K)>>,
+}
+
+impl<K> Default for DependencyGraph<K> {
+    fn default() -> Self {
+        DependencyGraph {
+            adjacency: HashMap::new(),
+        }
+    }
+}
+
+impl DependencyGraph {
+    /// The graph of session order and write-read edges.
+    pub fn new(history: &TransactionalHistory) -> Self {
+        let mut graph = DependencyGraph::default();
+        for session in history.sessions.iter() {
+            let mut prev = None;
+            for &txn in session.iter() {
+                graph.add_edge(prev, Some(txn), EdgeKind::SessionOrder);
+                prev = Some(txn);
+            }
+        }
+        for (&txn, info) in history.infos.iter() {
+            for read in info.reads.iter() {
+                if read.source.is_some() {
+                    graph.add_edge(read.source, Some(txn), EdgeKind::WriteRead(read.variable));
+                }
+            }
+        }
+        graph
+    }
+
+    /// Adds real-time edges between transactions of different sessions.
+    pub fn add_real_time(&mut self, history: &TransactionalHistory) {
+        for &txn in history.sessions.iter().flatten() {
+            for pred in history.real_time_predecessors(txn) {
+                self.add_edge(Some(pred), Some(txn), EdgeKind::RealTime);
+            }
+        }
+    }
+}
+
+impl<K: Copy + Eq> DependencyGraph<K> {
+    /// Adds an edge, returning `false` if it is already present.
+    pub fn add_edge(&mut self, from: Source, to: Source, kind: K) -> bool {
+        let successors = self.adjacency.entry(from).or_default();
+        if successors.contains(&(to, kind)) {
+            false
+        } else {
+            successors.push((to, kind));
+            true
+        }
+    }
+
+    pub fn successors(&self, node: &Source) -> &[(Source, K)] {
+        self.adjacency.get(node).map_or(&[][..], |s| &s[..])
+    }
+
+    /// Orders the given nodes so that every edge points forward, or returns a cycle.
+    pub fn topological_order(&self, nodes: &[Source]) -> Result<Vec<Source>, Vec<Edge<K>>> {
+        let mut in_degree: HashMap<Source, usize> = nodes.iter().map(|&node| (node, 0)).collect();
+        for node in nodes.iter() {
+            for (succ, _) in self.successors(node).iter() {
+                *in_degree.entry(*succ).or_default() += 1;
+            }
+        }
+
+        let mut ready: Vec<Source> = nodes.iter().copied().filter(|node| in_degree[node] == 0).collect();
+        let mut order = Vec::with_capacity(nodes.len());
+
+        while let Some(node) = ready.pop() {
+            order.push(node);
+            for (succ, _) in self.successors(&node).iter() {
+                let degree = in_degree.get_mut(succ).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(*succ);
+                }
+            }
+        }
+
+        if order.len() == in_degree.len() {
+            Ok(order)
+        } else {
+            Err(self.find_minimal_cycle().unwrap())
+        }
+    }
+
+    /// Finds a cycle and returns its edges in order.
+    pub fn find_cycle(&self) -> Option<Vec<Edge<K>>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Active,
+            Done,
+        }
+
+        let mut marks: HashMap<Source, Mark> = HashMap::new();
+        let mut roots: Vec<&Source> = self.adjacency.keys().collect();
+        roots.sort();
+
+        for &root in roots {
+            if marks.contains_key(&root) {
+                continue;
+            }
+            // each frame is a node and the index of its next successor to visit
+            let mut stack: Vec<(Source, usize)> = vec![(root, 0)];
+            let mut path: Vec<Edge<K>> = Vec::new();
+            marks.insert(root, Mark::Active);
+
+            while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+                if let Some(&(succ, kind)) = self.successors(&node).get(*next) {
+                    *next += 1;
+                    let edge = Edge { from: node, to: succ, kind };
+                    match marks.get(&succ) {
+                        Some(Mark::Active) => {
+                            let start = path.iter().position(|e| e.from == succ).unwrap_or(path.len());
+                            let mut cycle = path[start..].to_vec();
+                            cycle.push(edge);
+                            return Some(cycle);
+                        }
+                        Some(Mark::Done) => {}
+                        None => {
+                            marks.insert(succ, Mark::Active);
+                            path.push(edge);
+                            stack.push((succ, 0));
+                        }
+                    }
+                } else {
+                    marks.insert(node, Mark::Done);
+                    stack.pop();
+                    path.pop();
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Finds a cycle and shortens it to the shortest cycle through any of its nodes.
+    pub fn find_minimal_cycle(&self) -> Option<Vec<Edge<K>>> {
+        let cycle = self.find_cycle()?;
+        cycle
+            .iter()
+            .filter_map(|edge| self.shortest_cycle_through(edge.from))
+            .min_by_key(|cycle| cycle.len())
+    }
+
+    fn shortest_cycle_through(&self, start: Source) -> Option<Vec<Edge<K>>> {
+        self.shortest_path(start, start)
+    }
+
+    /// Finds a shortest non-empty path from `from` to `to`, a cycle if they are equal.
+    pub fn shortest_path(&self, from: Source, to: Source) -> Option<Vec<Edge<K>>> {
+        let mut parent: HashMap<Source, Edge<K>> = HashMap::new();
+        let mut queue = VecDeque::from(vec![from]);
+
+        while let Some(node) = queue.pop_front() {
+            for &(succ, kind) in self.successors(&node).iter() {
+                let edge = Edge { from: node, to: succ, kind };
+                if succ == to {
+                    let mut path = vec![edge];
+                    let mut curr = node;
+                    while curr != from {
+                        let edge = parent[&curr];
+                        path.push(edge);
+                        curr = edge.from;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                if let Entry::Vacant(entry) = parent.entry(succ) {
+                    entry.insert(edge);
+                    queue.push_back(succ);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Transitive predecessors of every node of an acyclic graph.
+pub struct Reachability {
+    index: HashMap<Source, usize>,
+    past: Vec<Vec<u64>>,
+}
+
+impl Reachability {
+    pub fn new<K: Copy + Eq>(graph: &DependencyGraph<K>, nodes: &[Source]) -> Result<Self, Vec<Edge<K>>> {
+        let order = graph.topological_order(nodes)?;
+        let index: HashMap<Source, usize> = nodes.iter().enumerate().map(|(i, &node)| (node, i)).collect();
+
+        // predecessors of every node as a bit set over `nodes`
+        let n_block = nodes.len().div_ceil(64);
+        let mut past = vec![vec![0u64; n_block]; nodes.len()];
+        for node in order.iter() {
+            let i = index[node];
+            for (succ, _) in graph.successors(node).iter() {
+                let j = index[succ];
+                let (from, to) = if i < j {
+                    let (left, right) = past.split_at_mut(j);
+                    (&left[i], &mut right[0])
+                } else {
+                    let (left, right) = past.split_at_mut(i);
+                    (&right[0], &mut left[j])
+                };
+                to.iter_mut().zip(from.iter()).for_each(|(t, f)| *t |= *f);
+                to[i / 64] |= 1 << (i % 64);
+            }
+        }
+
+        Ok(Reachability { index, past })
+    }
+
+    /// Whether there is a non-empty path from `from` to `to`.
+    pub fn reaches(&self, from: &Source, to: &Source) -> bool {
+        let (i, j) = (self.index[from], self.index[to]);
+        self.past[j][i / 64] & (1 << (i % 64)) != 0
+    }
+}
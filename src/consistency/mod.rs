@@ -0,0 +1,170 @@
+pub mod adya;
+pub mod causal;
+pub mod graph;
+pub mod ra;
+pub mod rc;
+pub mod sat;
+pub mod ser;
+pub mod session;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod util;
+pub mod witness;
+
+use std::fmt;
+
+use clap::ValueEnum;
+
+use graph::Edge;
+use session::SessionGuarantee;
+use util::{Source, TransactionId, Variable};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Consistency {
+    ReadCommitted,
+    ReadAtomic,
+    Causal,
+    SnapshotIsolation,
+    Serializable,
+    /// Serializable in an order extending the real-time order of the transactions.
+    StrictSerializable,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// Two writes carry the same value for a variable, so reads cannot be attributed.
+    AmbiguousWrite {
+        txns: (TransactionId, TransactionId),
+        variable: Variable,
+        value: usize,
+    },
+    /// A read returns a value that was never written.
+    ThinAirRead {
+        txn: TransactionId,
+        variable: Variable,
+        value: usize,
+    },
+    /// A read returns a value the transaction itself writes only later.
+    FutureRead {
+        txn: TransactionId,
+        variable: Variable,
+        value: usize,
+    },
+    /// A read returns a value written by an aborted transaction.
+    AbortedRead {
+        txn: TransactionId,
+        writer: TransactionId,
+        variable: Variable,
+        value: usize,
+    },
+    /// A read returns a value overwritten later by the same writer.
+    IntermediateRead {
+        txn: TransactionId,
+        writer: TransactionId,
+        variable: Variable,
+        value: usize,
+    },
+    /// A read does not return the value written earlier by the same transaction.
+    InternalRead {
+        txn: TransactionId,
+        variable: Variable,
+        expected: usize,
+        value: usize,
+    },
+    /// Two reads of a variable in a transaction return values from different writers.
+    NonRepeatableRead {
+        txn: TransactionId,
+        variable: Variable,
+        sources: (Source, Source),
+    },
+    /// No commit order of the committed transactions satisfies the consistency level.
+    /// Every choice of write-write order closes one of `cycles`, when they are known.
+    NoCommitOrder {
+        level: Consistency,
+        cycles: Vec<Vec<Edge>>,
+    },
+    /// The dependencies required by the consistency level form a cycle.
+    Cycle {
+        level: Consistency,
+        edges: Vec<Edge>,
+    },
+    /// The write order a session guarantee requires for a session forms a cycle.
+    SessionGuarantee {
+        guarantee: SessionGuarantee,
+        session: usize,
+        edges: Vec<Edge>,
+    },
+}
+
+fn source_repr(source: &Source) -> String {
+    match source {
+        Some(txn) => txn.to_string(),
+        None => "init".to_string(),
+    }
+}
+
+fn cycle_repr(edges: &[Edge]) -> String {
+    let mut repr = String::new();
+    for edge in edges.iter() {
+        repr += &format!("{} -{}-> ", source_repr(&edge.from), edge.kind);
+    }
+    if let Some(edge) = edges.first() {
+        repr += &source_repr(&edge.from);
+    }
+    repr
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::AmbiguousWrite { txns, variable, value } => write!(
+                f,
+                "{} and {} both write {} to x{}",
+                txns.0, txns.1, value, variable
+            ),
+            Violation::ThinAirRead { txn, variable, value } => {
+                write!(f, "{} reads {} from x{} which is never written", txn, value, variable)
+            }
+            Violation::FutureRead { txn, variable, value } => {
+                write!(f, "{} reads {} from x{} before writing it", txn, value, variable)
+            }
+            Violation::AbortedRead { txn, writer, variable, value } => write!(
+                f,
+                "{} reads {} from x{} written by aborted {}",
+                txn, value, variable, writer
+            ),
+            Violation::IntermediateRead { txn, writer, variable, value } => write!(
+                f,
+                "{} reads {} from x{} which {} overwrites",
+                txn, value, variable, writer
+            ),
+            Violation::InternalRead { txn, variable, expected, value } => write!(
+                f,
+                "{} reads {} from x{} after writing {}",
+                txn, value, variable, expected
+            ),
+            Violation::NonRepeatableRead { txn, variable, sources } => write!(
+                f,
+                "{} reads x{} from both {} and {}",
+                txn,
+                variable,
+                source_repr(&sources.0),
+                source_repr(&sources.1)
+            ),
+            Violation::NoCommitOrder { level, cycles } => {
+                write!(f, "no commit order satisfies {:?}", level)?;
+                if !cycles.is_empty() {
+                    let cycles: Vec<_> = cycles.iter().map(|edges| cycle_repr(edges)).collect();
+                    write!(f, ", every write order closes one of: {}", cycles.join("; "))?;
+                }
+                Ok(())
+            }
+            Violation::Cycle { level, edges } => {
+                write!(f, "{:?} cycle: {}", level, cycle_repr(edges))
+            }
+            Violation::SessionGuarantee { guarantee, session, edges } => {
+                write!(f, "session {} breaks {:?}: {}", session, guarantee, cycle_repr(edges))
+            }
+        }
+    }
+}
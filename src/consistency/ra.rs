@@ -0,0 +1,77 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::graph::{DependencyGraph, EdgeKind};
+use super::util::{TransactionalHistory, Variable};
+use super::{Consistency, Violation};
+
+/// Checks Read Atomic.
+///
+/// If a transaction reads `x` from `t1` and a different writer `t2` of `x` precedes it by
+/// session order or write-read, then `t2` must be ordered before `t1`. The history is Read
+/// Atomic iff these write-write edges together with session order and write-read are acyclic.
+pub fn check_read_atomic(history: &TransactionalHistory) -> Result<(), Violation> {
+    let mut graph = DependencyGraph::new(history);
+
+    for session in history.sessions.iter() {
+        let mut last_writer: HashMap<Variable, _> = HashMap::new();
+        for &txn in session.iter() {
+            let info = &history.infos[&txn];
+            let sources: BTreeSet<_> = info.reads.iter().filter_map(|r| r.source).collect();
+            for read in info.reads.iter() {
+                let x = read.variable;
+                for &writer in last_writer.get(&x).into_iter().chain(sources.iter()) {
+                    if Some(writer) != read.source && history.infos[&writer].writes.contains(&x) {
+                        let kind = EdgeKind::WriteWrite { variable: x, reader: Some(txn) };
+                        graph.add_edge(Some(writer), read.source, kind);
+                    }
+                }
+            }
+            for &x in info.writes.iter() {
+                last_writer.insert(x, txn);
+            }
+        }
+    }
+
+    match graph.find_minimal_cycle() {
+        Some(edges) => Err(Violation::Cycle {
+            level: Consistency::ReadAtomic,
+            edges,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::testing::{committed, history, read};
+    use crate::consistency::witness::Witness;
+    use crate::db::history::Event;
+
+    #[test]
+    fn write_skew_is_allowed() {
+        let histories = vec![
+            vec![committed(vec![read(0, 0), Event::write(1, 1)])],
+            vec![committed(vec![read(1, 0), Event::write(0, 1)])],
+        ];
+        assert_eq!(check_read_atomic(&history(&histories)), Ok(()));
+    }
+
+    #[test]
+    fn fractured_read_is_forbidden() {
+        // T(1,0) sees the write of x0 but not the write of x1 by the same transaction
+        let histories = vec![
+            vec![committed(vec![Event::write(0, 1), Event::write(1, 1)])],
+            vec![committed(vec![read(1, 0), read(0, 1)])],
+        ];
+        let violation = check_read_atomic(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "ReadAtomic cycle: init -so-> T(0,0) -ww(x1)-> init");
+        assert_eq!(
+            Witness::new(&violation, &histories).unwrap().to_string(),
+            concat!(
+                "  init -so-> T(0,0)\n",
+                "  T(0,0) -ww(x1)-> init  T(0,0)[1]<W(1): 1>  T(1,0)[0]<R(1): 0>\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use super::graph::{DependencyGraph, EdgeKind};
+use super::util::{TransactionalHistory, Variable};
+use super::{Consistency, Violation};
+
+/// Checks Read Committed.
+///
+/// If a read of `x` takes its value from `t1` while a different writer `t2` of `x` is
+/// already visible to the reading transaction, either by session order or through one of
+/// its earlier reads, then `t2` must be ordered before `t1`. The history is Read Committed
+/// iff these write-write edges together with session order and write-read are acyclic.
+pub fn check_read_committed(history: &TransactionalHistory) -> Result<(), Violation> {
+    let mut graph = DependencyGraph::new(history);
+
+    for session in history.sessions.iter() {
+        let mut last_writer: HashMap<Variable, _> = HashMap::new();
+        for &txn in session.iter() {
+            let info = &history.infos[&txn];
+            for (i_read, read) in info.reads.iter().enumerate() {
+                let x = read.variable;
+                let earlier_sources = info.reads[..i_read].iter().filter_map(|r| r.source);
+                for writer in last_writer.get(&x).copied().into_iter().chain(earlier_sources) {
+                    if Some(writer) != read.source && history.infos[&writer].writes.contains(&x) {
+                        let kind = EdgeKind::WriteWrite { variable: x, reader: Some(txn) };
+                        graph.add_edge(Some(writer), read.source, kind);
+                    }
+                }
+            }
+            for &x in info.writes.iter() {
+                last_writer.insert(x, txn);
+            }
+        }
+    }
+
+    match graph.find_minimal_cycle() {
+        Some(edges) => Err(Violation::Cycle {
+            level: Consistency::ReadCommitted,
+            edges,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::testing::{committed, history, read};
+    use crate::consistency::witness::Witness;
+    use crate::db::history::Event;
+
+    #[test]
+    fn fractured_read_is_allowed() {
+        let histories = vec![
+            vec![committed(vec![Event::write(0, 1), Event::write(1, 1)])],
+            vec![committed(vec![read(1, 0), read(0, 1)])],
+        ];
+        assert_eq!(check_read_committed(&history(&histories)), Ok(()));
+    }
+
+    #[test]
+    fn read_of_overwritten_value_is_forbidden() {
+        // the second read returns the initial value after the first one saw it overwritten
+        let histories = vec![
+            vec![committed(vec![Event::write(0, 1)])],
+            vec![committed(vec![read(0, 1), read(0, 0)])],
+        ];
+        let violation = check_read_committed(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "ReadCommitted cycle: init -so-> T(0,0) -ww(x0)-> init");
+        assert_eq!(
+            Witness::new(&violation, &histories).unwrap().to_string(),
+            concat!(
+                "  init -so-> T(0,0)\n",
+                "  T(0,0) -ww(x0)-> init  T(0,0)[0]<W(0): 1>  T(1,0)[1]<R(0): 0>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn session_reading_before_its_own_write_is_forbidden() {
+        let histories = vec![vec![
+            committed(vec![Event::write(0, 1)]),
+            committed(vec![read(0, 0)]),
+        ]];
+        let violation = check_read_committed(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "ReadCommitted cycle: init -so-> T(0,0) -ww(x0)-> init");
+    }
+}
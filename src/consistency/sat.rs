@@ -0,0 +1,293 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use varisat::{ExtendFormula, Lit, Solver};
+
+use super::causal::check_causal;
+use super::graph::{DependencyGraph, Edge, EdgeKind, Reachability};
+use super::util::{TransactionId, TransactionalHistory, Variable};
+use super::{Consistency, Violation};
+
+/// Whether a write of a variable is ordered before another write of it.
+#[derive(Clone, Copy)]
+enum Order {
+    Always,
+    Never,
+    When(Lit),
+}
+
+impl Order {
+    fn reverse(self) -> Order {
+        match self {
+            Order::Always => Order::Never,
+            Order::Never => Order::Always,
+            Order::When(lit) => Order::When(!lit),
+        }
+    }
+
+    /// `None` if the order never holds, otherwise the literal it depends on, if any.
+    fn condition(self) -> Option<Option<Lit>> {
+        match self {
+            Order::Always => Some(None),
+            Order::Never => None,
+            Order::When(lit) => Some(Some(lit)),
+        }
+    }
+}
+
+/// A dependency edge present in every model satisfying its literal.
+struct Constraint {
+    edge: Edge,
+    when: Option<Lit>,
+}
+
+pub fn check_snapshot_isolation(history: &TransactionalHistory) -> Result<(), Violation> {
+    check(history, Consistency::SnapshotIsolation)
+}
+
+pub fn check_serializable(history: &TransactionalHistory) -> Result<(), Violation> {
+    check(history, Consistency::Serializable)
+}
+
+pub fn check_strict_serializable(history: &TransactionalHistory) -> Result<(), Violation> {
+    check(history, Consistency::StrictSerializable)
+}
+
+/// Checks snapshot isolation or serializability with a SAT solver.
+///
+/// Every pair of writes of a variable that is not already ordered by the causal order gets
+/// a boolean variable for its write-write order, which in turn decides the read-write edges
+/// of the readers of the earlier write. The history is serializable iff some assignment makes
+/// the session order, write-read, write-write and read-write edges acyclic. It satisfies
+/// snapshot isolation iff some assignment makes the dependencies (session order, write-read
+/// and write-write), each optionally followed by a read-write edge, acyclic. Strict
+/// serializability additionally includes real-time edges.
+///
+/// Acyclicity is enforced lazily: every cycle found in a model is excluded by a clause over
+/// the literals of its edges and the solver is asked again. Each clause is guarded by an
+/// assumption so that an unsatisfiable core names the cycles ruling out every write order.
+fn check(history: &TransactionalHistory, level: Consistency) -> Result<(), Violation> {
+    check_causal(history).map_err(|violation| match violation {
+        Violation::Cycle { edges, .. } => Violation::Cycle { level, edges },
+        violation => violation,
+    })?;
+
+    let nodes = history.nodes();
+    let mut base = DependencyGraph::new(history);
+    if level == Consistency::StrictSerializable {
+        base.add_real_time(history);
+    }
+    let causal_past = Reachability::new(&base, &nodes).map_err(|edges| Violation::Cycle { level, edges })?;
+
+    let mut solver = Solver::new();
+    let mut write_order: HashMap<(Variable, TransactionId, TransactionId), Order> = HashMap::new();
+    for (&x, writers) in history.writers.iter() {
+        for (i, &a) in writers.iter().enumerate() {
+            for &b in writers[i + 1..].iter() {
+                let order = if causal_past.reaches(&Some(a), &Some(b)) {
+                    Order::Always
+                } else if causal_past.reaches(&Some(b), &Some(a)) {
+                    Order::Never
+                } else {
+                    Order::When(solver.new_lit())
+                };
+                write_order.insert((x, a, b), order);
+                write_order.insert((x, b, a), order.reverse());
+            }
+        }
+    }
+
+    let mut dependencies = Vec::new();
+    for node in nodes.iter() {
+        for &(succ, kind) in base.successors(node).iter() {
+            let edge = Edge { from: *node, to: succ, kind };
+            dependencies.push(Constraint { edge, when: None });
+        }
+    }
+    for (&x, writers) in history.writers.iter() {
+        for &a in writers.iter() {
+            for &b in writers.iter().filter(|&&b| b != a) {
+                if let Some(when) = write_order[&(x, a, b)].condition() {
+                    let edge = Edge { from: Some(a), to: Some(b), kind: EdgeKind::WriteWrite { variable: x, reader: None } };
+                    dependencies.push(Constraint { edge, when });
+                }
+            }
+        }
+    }
+
+    let mut anti_dependencies = Vec::new();
+    for (&txn, info) in history.infos.iter() {
+        let reads: BTreeSet<_> = info.reads.iter().map(|read| (read.variable, read.source)).collect();
+        for &(x, source) in reads.iter() {
+            let writers = history.writers.get(&x).map_or(&[][..], |w| &w[..]);
+            for &writer in writers.iter().filter(|&&w| w != txn && Some(w) != source) {
+                let condition = match source {
+                    Some(source) => write_order[&(x, source, writer)].condition(),
+                    None => Some(None),
+                };
+                if let Some(when) = condition {
+                    let edge = Edge { from: Some(txn), to: Some(writer), kind: EdgeKind::ReadWrite(x) };
+                    anti_dependencies.push(Constraint { edge, when });
+                }
+            }
+        }
+    }
+
+    let constraints: Vec<&Constraint> = dependencies.iter().chain(anti_dependencies.iter()).collect();
+    let n_dependency = dependencies.len();
+
+    let mut cycles: Vec<Vec<Edge>> = Vec::new();
+    let mut guards: Vec<Lit> = Vec::new();
+
+    loop {
+        solver.assume(&guards);
+        if !solver.solve().expect("SAT solver failed") {
+            let core: HashSet<Lit> = solver.failed_core().unwrap_or(&[]).iter().copied().collect();
+            let cycles = guards
+                .iter()
+                .zip(cycles.drain(..))
+                .filter(|(guard, _)| core.contains(guard))
+                .map(|(_, cycle)| cycle)
+                .collect();
+            return Err(Violation::NoCommitOrder { level, cycles });
+        }
+
+        let model: HashSet<Lit> = solver.model().unwrap().into_iter().collect();
+        let holds = |i: &usize| match constraints[*i].when {
+            Some(lit) => model.contains(&lit),
+            None => true,
+        };
+        let active_dependencies: Vec<usize> = (0..n_dependency).filter(holds).collect();
+        let active_anti_dependencies: Vec<usize> = (n_dependency..constraints.len()).filter(holds).collect();
+
+        // every edge of the graph stands for a path of constraints
+        let mut paths: Vec<Vec<usize>> = Vec::new();
+        let mut graph: DependencyGraph<usize> = DependencyGraph::default();
+        let mut add_path = |path: Vec<usize>| {
+            let from = constraints[path[0]].edge.from;
+            let to = constraints[path[path.len() - 1]].edge.to;
+            graph.add_edge(from, to, paths.len());
+            paths.push(path);
+        };
+
+        match level {
+            Consistency::SnapshotIsolation => {
+                let mut anti_from: HashMap<_, Vec<usize>> = HashMap::new();
+                for &j in active_anti_dependencies.iter() {
+                    anti_from.entry(constraints[j].edge.from).or_default().push(j);
+                }
+                for &i in active_dependencies.iter() {
+                    add_path(vec![i]);
+                    for &j in anti_from.get(&constraints[i].edge.to).into_iter().flatten() {
+                        add_path(vec![i, j]);
+                    }
+                }
+            }
+            _ => {
+                for &i in active_dependencies.iter().chain(active_anti_dependencies.iter()) {
+                    add_path(vec![i]);
+                }
+            }
+        }
+
+        let cycle = match graph.find_minimal_cycle() {
+            Some(cycle) => cycle,
+            None => return Ok(()),
+        };
+
+        let used: Vec<&Constraint> = cycle
+            .iter()
+            .flat_map(|edge| paths[edge.kind].iter())
+            .map(|&i| constraints[i])
+            .collect();
+        let edges: Vec<Edge> = used.iter().map(|c| c.edge).collect();
+        let blocking: BTreeSet<Lit> = used.iter().filter_map(|c| c.when).map(|lit| !lit).collect();
+
+        if blocking.is_empty() {
+            return Err(Violation::Cycle { level, edges });
+        }
+
+        let guard = solver.new_lit();
+        let mut clause: Vec<Lit> = blocking.into_iter().collect();
+        clause.push(!guard);
+        solver.add_clause(&clause);
+        guards.push(guard);
+        cycles.push(edges);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::testing::{committed, history, read, timed};
+    use crate::consistency::witness::Witness;
+    use crate::db::history::Event;
+
+    #[test]
+    fn write_skew_is_snapshot_isolation_but_not_serializable() {
+        let histories = vec![
+            vec![committed(vec![read(0, 0), Event::write(1, 1)])],
+            vec![committed(vec![read(1, 0), Event::write(0, 1)])],
+        ];
+        assert_eq!(check_snapshot_isolation(&history(&histories)), Ok(()));
+        let violation = check_serializable(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "Serializable cycle: T(0,0) -rw(x0)-> T(1,0) -rw(x1)-> T(0,0)");
+        assert_eq!(
+            Witness::new(&violation, &histories).unwrap().to_string(),
+            concat!(
+                "  T(0,0) -rw(x0)-> T(1,0)  T(0,0)[0]<R(0): 0>  T(1,0)[1]<W(0): 1>\n",
+                "  T(1,0) -rw(x1)-> T(0,0)  T(1,0)[0]<R(1): 0>  T(0,0)[1]<W(1): 1>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn lost_update_is_not_snapshot_isolation() {
+        let histories = vec![
+            vec![committed(vec![read(0, 0), Event::write(0, 1)])],
+            vec![committed(vec![read(0, 0), Event::write(0, 2)])],
+        ];
+        let violation = check_snapshot_isolation(&history(&histories)).unwrap_err();
+        assert_eq!(
+            violation.to_string(),
+            concat!(
+                "no commit order satisfies SnapshotIsolation, every write order closes one of: ",
+                "T(0,0) -ww(x0)-> T(1,0) -rw(x0)-> T(0,0); T(1,0) -ww(x0)-> T(0,0) -rw(x0)-> T(1,0)",
+            )
+        );
+        assert_eq!(
+            Witness::new(&violation, &histories).unwrap().to_string(),
+            concat!(
+                "cycle 1:\n",
+                "  T(0,0) -ww(x0)-> T(1,0)  T(0,0)[1]<W(0): 1>  T(1,0)[1]<W(0): 2>\n",
+                "  T(1,0) -rw(x0)-> T(0,0)  T(1,0)[0]<R(0): 0>  T(0,0)[1]<W(0): 1>\n",
+                "cycle 2:\n",
+                "  T(1,0) -ww(x0)-> T(0,0)  T(1,0)[1]<W(0): 2>  T(0,0)[1]<W(0): 1>\n",
+                "  T(0,0) -rw(x0)-> T(1,0)  T(0,0)[0]<R(0): 0>  T(1,0)[1]<W(0): 2>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn long_fork_is_not_snapshot_isolation() {
+        // two readers see the independent writes of x0 and x1 in opposite orders
+        let histories = vec![
+            vec![committed(vec![Event::write(0, 1)])],
+            vec![committed(vec![Event::write(1, 1)])],
+            vec![committed(vec![read(0, 1), read(1, 0)])],
+            vec![committed(vec![read(0, 0), read(1, 1)])],
+        ];
+        let violation = check_snapshot_isolation(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "SnapshotIsolation cycle: T(0,0) -wr(x0)-> T(2,0) -rw(x1)-> T(1,0) -wr(x1)-> T(3,0) -rw(x0)-> T(0,0)");
+    }
+
+    #[test]
+    fn stale_read_after_commit_is_not_strict() {
+        let histories = vec![
+            vec![timed(vec![Event::write(0, 1)], 0, 10)],
+            vec![timed(vec![read(0, 0)], 20, 30)],
+        ];
+        assert_eq!(check_serializable(&history(&histories)), Ok(()));
+        let violation = check_strict_serializable(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "StrictSerializable cycle: T(0,0) -rt-> T(1,0) -rw(x0)-> T(0,0)");
+    }
+}
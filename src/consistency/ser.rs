@@ -0,0 +1,149 @@
+use std::collections::{HashMap, HashSet};
+
+use super::util::{Source, TransactionId, TransactionalHistory, Variable};
+use super::{Consistency, Violation};
+
+pub fn check_serializable(history: &TransactionalHistory) -> Result<(), Violation> {
+    search(history, Consistency::Serializable)
+}
+
+pub fn check_strict_serializable(history: &TransactionalHistory) -> Result<(), Violation> {
+    search(history, Consistency::StrictSerializable)
+}
+
+/// Searches for a serial order of the committed transactions.
+///
+/// A search state is the downward closed set of already ordered transactions, given as
+/// the length of the ordered prefix of each session. A transaction can be ordered next
+/// if all its reads are from ordered transactions and its writes do not overwrite a value
+/// that a pending transaction still has to read. The number of states is polynomial in
+/// the number of transactions for a fixed number of sessions. For strict serializability
+/// a transaction is also ordered after every transaction committing before it starts.
+fn search(history: &TransactionalHistory, level: Consistency) -> Result<(), Violation> {
+    let mut read_sources: HashMap<TransactionId, HashMap<Variable, Source>> = HashMap::new();
+    let mut readers: HashMap<Variable, Vec<(TransactionId, Source)>> = HashMap::new();
+
+    for (&txn, info) in history.infos.iter() {
+        let sources = info.read_sources().map_err(|(variable, first, second)| {
+            Violation::NonRepeatableRead {
+                txn,
+                variable,
+                sources: (first, second),
+            }
+        })?;
+        for (&variable, &source) in sources.iter() {
+            readers.entry(variable).or_default().push((txn, source));
+        }
+        read_sources.insert(txn, sources);
+    }
+
+    let position: HashMap<TransactionId, usize> = history
+        .sessions
+        .iter()
+        .flat_map(|session| session.iter().enumerate().map(|(pos, &txn)| (txn, pos)))
+        .collect();
+
+    let real_time: HashMap<TransactionId, Vec<TransactionId>> = match level {
+        Consistency::StrictSerializable => history
+            .infos
+            .keys()
+            .map(|&txn| (txn, history.real_time_predecessors(txn)))
+            .collect(),
+        _ => HashMap::new(),
+    };
+
+    // the initial state is always ordered
+    let is_ordered = |state: &[usize], source: &Source| match source {
+        Some(txn) => position[txn] < state[txn.session],
+        None => true,
+    };
+
+    let can_order = |state: &[usize], txn: TransactionId| -> bool {
+        let sources_ordered = read_sources[&txn].values().all(|source| is_ordered(state, source));
+        let no_pending_reader = |variable: &Variable| match readers.get(variable) {
+            Some(rs) => rs.iter().all(|(reader, source)| {
+                *reader == txn || is_ordered(state, &Some(*reader)) || !is_ordered(state, source)
+            }),
+            None => true,
+        };
+        let preceding_ordered = match real_time.get(&txn) {
+            Some(preds) => preds.iter().all(|&pred| is_ordered(state, &Some(pred))),
+            None => true,
+        };
+        sources_ordered && preceding_ordered && history.infos[&txn].writes.iter().all(no_pending_reader)
+    };
+
+    let complete: Vec<usize> = history.sessions.iter().map(|session| session.len()).collect();
+    let mut visited = HashSet::new();
+    let mut stack = vec![vec![0; history.sessions.len()]];
+
+    while let Some(state) = stack.pop() {
+        if state == complete {
+            return Ok(());
+        }
+        if !visited.insert(state.clone()) {
+            continue;
+        }
+        for (i_session, session) in history.sessions.iter().enumerate() {
+            if let Some(&txn) = session.get(state[i_session]) {
+                if can_order(&state, txn) {
+                    let mut next = state.clone();
+                    next[i_session] += 1;
+                    if !visited.contains(&next) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+    }
+
+    Err(Violation::NoCommitOrder {
+        level,
+        cycles: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::testing::{committed, history, read, timed};
+    use crate::db::history::Event;
+
+    #[test]
+    fn serial_history_is_allowed() {
+        let histories = vec![
+            vec![committed(vec![read(0, 0), Event::write(0, 1)]), committed(vec![read(1, 1)])],
+            vec![committed(vec![read(0, 1), Event::write(0, 2), Event::write(1, 1)])],
+        ];
+        assert_eq!(check_serializable(&history(&histories)), Ok(()));
+    }
+
+    #[test]
+    fn write_skew_is_forbidden() {
+        let histories = vec![
+            vec![committed(vec![read(0, 0), Event::write(1, 1)])],
+            vec![committed(vec![read(1, 0), Event::write(0, 1)])],
+        ];
+        let violation = check_serializable(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "no commit order satisfies Serializable");
+    }
+
+    #[test]
+    fn stale_read_after_commit_is_not_strict() {
+        // T(1,0) starts after T(0,0) commits but reads the initial value
+        let histories = vec![
+            vec![timed(vec![Event::write(0, 1)], 0, 10)],
+            vec![timed(vec![read(0, 0)], 20, 30)],
+        ];
+        assert_eq!(check_serializable(&history(&histories)), Ok(()));
+        let violation = check_strict_serializable(&history(&histories)).unwrap_err();
+        assert_eq!(violation.to_string(), "no commit order satisfies StrictSerializable");
+
+        // overlapping transactions can be ordered either way
+        let histories = vec![
+            vec![timed(vec![Event::write(0, 1)], 0, 25)],
+            vec![timed(vec![read(0, 0)], 20, 30)],
+        ];
+        assert_eq!(check_strict_serializable(&history(&histories)), Ok(()));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+
+use super::graph::{DependencyGraph, Edge, EdgeKind};
+use super::util::{Source, TransactionId, TransactionalHistory, Variable};
+use super::Violation;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum SessionGuarantee {
+    ReadYourWrites,
+    MonotonicReads,
+    MonotonicWrites,
+    WritesFollowReads,
+}
+
+/// Checks a session guarantee, returning a violation for every session that breaks it.
+///
+/// Guarantees relate transactions of a session ordered by session order; guarantees within
+/// a transaction are left to the consistency levels. Each guarantee forces the version order
+/// of a variable in a way observable from the values read:
+///
+/// - read your writes: a transaction reads `x` from a write no older than any write of `x`
+///   earlier in its session.
+/// - monotonic reads: a transaction reads `x` from a write no older than the ones earlier
+///   transactions of its session read `x` from.
+/// - monotonic writes: a transaction reading from `t2` reads `x` from a write no older than
+///   any write of `x` by a transaction preceding `t2` in its session.
+/// - writes follow reads: a transaction reading from `t2` reads `x` from a write no older than
+///   any write of `x` read by a transaction preceding `t2` in its session.
+///
+/// The session breaks the guarantee iff the required order on some variable, with the
+/// initial state first, is cyclic.
+pub fn check_session_guarantee(history: &TransactionalHistory, guarantee: SessionGuarantee) -> Vec<Violation> {
+    // write-write edges required by each session for each variable, with the reader forcing them
+    let mut requirements: BTreeMap<(usize, Variable), Vec<Edge>> = BTreeMap::new();
+    let mut require = |session: usize, variable: Variable, writer: TransactionId, source: Source, reader: TransactionId| {
+        if Some(writer) != source {
+            let kind = EdgeKind::WriteWrite { variable, reader: Some(reader) };
+            let edge = Edge { from: Some(writer), to: source, kind };
+            requirements.entry((session, variable)).or_default().push(edge);
+        }
+    };
+
+    for (i_session, session) in history.sessions.iter().enumerate() {
+        for (i_txn, &txn) in session.iter().enumerate() {
+            let earlier = &session[..i_txn];
+            match guarantee {
+                SessionGuarantee::ReadYourWrites => {
+                    for read in history.infos[&txn].reads.iter() {
+                        for &writer in earlier.iter() {
+                            if history.infos[&writer].writes.contains(&read.variable) {
+                                require(i_session, read.variable, writer, read.source, txn);
+                            }
+                        }
+                    }
+                }
+                SessionGuarantee::MonotonicReads => {
+                    for read in history.infos[&txn].reads.iter() {
+                        for &prev in earlier.iter() {
+                            for prev_read in history.infos[&prev].reads.iter() {
+                                if let (Some(writer), true) = (prev_read.source, prev_read.variable == read.variable) {
+                                    require(i_session, read.variable, writer, read.source, txn);
+                                }
+                            }
+                        }
+                    }
+                }
+                SessionGuarantee::MonotonicWrites | SessionGuarantee::WritesFollowReads => {}
+            }
+        }
+    }
+
+    if let SessionGuarantee::MonotonicWrites | SessionGuarantee::WritesFollowReads = guarantee {
+        let position: BTreeMap<TransactionId, usize> = history
+            .sessions
+            .iter()
+            .flat_map(|session| session.iter().enumerate().map(|(pos, &txn)| (txn, pos)))
+            .collect();
+
+        for (&reader, info) in history.infos.iter() {
+            for visible in info.reads.iter().filter_map(|read| read.source) {
+                let i_session = visible.session;
+                let earlier = &history.sessions[i_session][..position[&visible]];
+                // writes the session requires to be visible to `reader`
+                let mut required: Vec<(TransactionId, Variable)> = Vec::new();
+                for &prev in earlier.iter() {
+                    match guarantee {
+                        SessionGuarantee::MonotonicWrites => {
+                            required.extend(history.infos[&prev].writes.iter().map(|&x| (prev, x)));
+                        }
+                        _ => required.extend(
+                            history.infos[&prev].reads.iter().filter_map(|read| Some((read.source?, read.variable))),
+                        ),
+                    }
+                }
+                for read in info.reads.iter() {
+                    for &(writer, x) in required.iter().filter(|&&(_, x)| x == read.variable) {
+                        require(i_session, x, writer, read.source, reader);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut violations = Vec::new();
+    let mut violating_session = None;
+    for (&(i_session, variable), edges) in requirements.iter() {
+        if violating_session == Some(i_session) {
+            continue;
+        }
+        let mut graph = DependencyGraph::default();
+        for edge in edges.iter() {
+            graph.add_edge(edge.from, edge.to, edge.kind);
+        }
+        for &writer in history.writers[&variable].iter() {
+            let kind = EdgeKind::WriteWrite { variable, reader: None };
+            graph.add_edge(None, Some(writer), kind);
+        }
+        if let Some(edges) = graph.find_minimal_cycle() {
+            violations.push(Violation::SessionGuarantee {
+                guarantee,
+                session: i_session,
+                edges,
+            });
+            violating_session = Some(i_session);
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::testing::{committed, history, read};
+    use crate::db::history::{Event, Session};
+
+    fn violations(histories: &[Session], guarantee: SessionGuarantee) -> Vec<String> {
+        check_session_guarantee(&history(histories), guarantee)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn read_your_writes() {
+        let histories = vec![vec![committed(vec![Event::write(0, 1)]), committed(vec![read(0, 0)])]];
+        assert_eq!(violations(&histories, SessionGuarantee::ReadYourWrites), ["session 0 breaks ReadYourWrites: init -ww(x0)-> T(0,0) -ww(x0)-> init"]);
+        assert!(violations(&histories, SessionGuarantee::MonotonicReads).is_empty());
+    }
+
+    #[test]
+    fn monotonic_reads() {
+        let histories = vec![
+            vec![committed(vec![Event::write(0, 1)])],
+            vec![committed(vec![read(0, 1)]), committed(vec![read(0, 0)])],
+        ];
+        assert_eq!(violations(&histories, SessionGuarantee::MonotonicReads), ["session 1 breaks MonotonicReads: init -ww(x0)-> T(0,0) -ww(x0)-> init"]);
+        assert!(violations(&histories, SessionGuarantee::ReadYourWrites).is_empty());
+    }
+
+    #[test]
+    fn monotonic_writes() {
+        // session 1 sees the second write of session 0 but not the first
+        let histories = vec![
+            vec![committed(vec![Event::write(0, 1)]), committed(vec![Event::write(1, 1)])],
+            vec![committed(vec![read(1, 1), read(0, 0)])],
+        ];
+        assert_eq!(violations(&histories, SessionGuarantee::MonotonicWrites), ["session 0 breaks MonotonicWrites: init -ww(x0)-> T(0,0) -ww(x0)-> init"]);
+        assert!(violations(&histories, SessionGuarantee::WritesFollowReads).is_empty());
+    }
+
+    #[test]
+    fn writes_follow_reads() {
+        // session 2 sees the write session 1 made after reading x0 = 1, but not x0 = 1
+        let histories = vec![
+            vec![committed(vec![Event::write(0, 1)])],
+            vec![committed(vec![read(0, 1)]), committed(vec![Event::write(1, 1)])],
+            vec![committed(vec![read(1, 1), read(0, 0)])],
+        ];
+        assert_eq!(violations(&histories, SessionGuarantee::WritesFollowReads), ["session 1 breaks WritesFollowReads: init -ww(x0)-> T(0,0) -ww(x0)-> init"]);
+        assert!(violations(&histories, SessionGuarantee::MonotonicWrites).is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+//! Builders of hand-written histories for the tests of the checkers.
+
+use crate::db::history::{Attempt, Event, Session, Timestamp, Transaction};
+
+use super::util::TransactionalHistory;
+
+/// An aborted transaction.
+pub fn transaction(events: Vec<Event>) -> Transaction {
+    Transaction { events, success: false, attempt: None, abort: None, gave_up: false }
+}
+
+/// A committed transaction, every event of it successful.
+pub fn committed(events: Vec<Event>) -> Transaction {
+    let mut transaction = transaction(events);
+    transaction.success = true;
+    for event in transaction.events.iter_mut() {
+        event.success = true;
+    }
+    transaction
+}
+
+/// A committed transaction attempted between the monotonic instants `start` and `end`.
+pub fn timed(events: Vec<Event>, start: u64, end: u64) -> Transaction {
+    let now = Timestamp::now();
+    let mut transaction = committed(events);
+    transaction.attempt = Some(Attempt {
+        start: Timestamp { monotonic: start, ..now },
+        end: Timestamp { monotonic: end, ..now },
+    });
+    transaction
+}
+
+/// A read of `variable` returning `value`.
+pub fn read(variable: usize, value: usize) -> Event {
+    let mut event = Event::read(variable);
+    event.value = value;
+    event
+}
+
+pub fn history(histories: &[Session]) -> TransactionalHistory {
+    TransactionalHistory::new(histories).unwrap()
+}
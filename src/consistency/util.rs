@@ -0,0 +1,232 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::history::{Attempt, Session};
+
+use super::Violation;
+
+pub type Variable = usize;
+
+/// Identifies a transaction by its session and its position in that session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TransactionId {
+    pub session: usize,
+    pub index: usize,
+}
+
+impl fmt::Display for TransactionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "T({},{})", self.session, self.index)
+    }
+}
+
+/// The transaction a read takes its value from, `None` being the initial state.
+pub type Source = Option<TransactionId>;
+
+#[derive(Clone, Debug)]
+pub struct ExternalRead {
+    pub event: usize,
+    pub variable: Variable,
+    pub source: Source,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TransactionInfo {
+    /// Reads not preceded by a write of the same variable in the transaction, in program order.
+    pub reads: Vec<ExternalRead>,
+    /// Variables whose last write in the transaction is visible to others.
+    pub writes: BTreeSet<Variable>,
+    /// The committed attempt, if its timing was recorded.
+    pub attempt: Option<Attempt>,
+}
+
+impl TransactionInfo {
+    /// The single source of each variable read, if all reads of a variable agree.
+    pub fn read_sources(&self) -> Result<HashMap<Variable, Source>, (Variable, Source, Source)> {
+        let mut sources = HashMap::new();
+        for read in self.reads.iter() {
+            match sources.insert(read.variable, read.source) {
+                Some(source) if source != read.source => {
+                    return Err((read.variable, source, read.source));
+                }
+                _ => {}
+            }
+        }
+        Ok(sources)
+    }
+}
+
+struct WriteInfo {
+    txn: TransactionId,
+    committed: bool,
+    last: bool,
+}
+
+/// Committed transactions of a history with their write-read relation resolved.
+pub struct TransactionalHistory {
+    /// Committed transactions of each session, in session order.
+    pub sessions: Vec<Vec<TransactionId>>,
+    pub infos: BTreeMap<TransactionId, TransactionInfo>,
+    /// Committed transactions writing each variable.
+    pub writers: BTreeMap<Variable, Vec<TransactionId>>,
+}
+
+impl TransactionalHistory {
+    pub fn new(histories: &[Session]) -> Result<Self, Violation> {
+        let (history, anomalies) = TransactionalHistory::with_anomalies(histories);
+        match anomalies.into_iter().next() {
+            Some(violation) => Err(violation),
+            None => Ok(history),
+        }
+    }
+
+    /// Builds the history even if some reads cannot be attributed to a committed final write.
+    /// Such reads are left out and reported, in the order they occur.
+    pub fn with_anomalies(histories: &[Session]) -> (Self, Vec<Violation>) {
+        let mut anomalies = Vec::new();
+        let mut write_index: HashMap<(Variable, usize), WriteInfo> = HashMap::new();
+
+        for (i_session, session) in histories.iter().enumerate() {
+            for (i_txn, transaction) in session.iter().enumerate() {
+                let txn = TransactionId { session: i_session, index: i_txn };
+                let writes: Vec<_> = transaction
+                    .events
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, event)| event.write && event.success)
+                    .collect();
+                for (i_event, event) in writes.iter() {
+                    let last = !writes
+                        .iter()
+                        .any(|(j_event, other)| j_event > i_event && other.variable == event.variable);
+                    let info = WriteInfo { txn, committed: transaction.success, last };
+                    match write_index.entry((event.variable, event.value)) {
+                        // attempts recorded before they wrote values of their own write the
+                        // same values, reads are attributed to the committed one
+                        Entry::Occupied(mut other) => match (other.get().committed, info.committed) {
+                            (true, true) => anomalies.push(Violation::AmbiguousWrite {
+                                txns: (other.get().txn, txn),
+                                variable: event.variable,
+                                value: event.value,
+                            }),
+                            (false, true) => {
+                                other.insert(info);
+                            }
+                            _ => {}
+                        },
+                        Entry::Vacant(entry) => {
+                            entry.insert(info);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut sessions = Vec::with_capacity(histories.len());
+        let mut infos = BTreeMap::new();
+        let mut writers: BTreeMap<Variable, Vec<TransactionId>> = BTreeMap::new();
+
+        for (i_session, session) in histories.iter().enumerate() {
+            let mut committed = Vec::new();
+            for (i_txn, transaction) in session.iter().enumerate() {
+                if !transaction.success {
+                    continue;
+                }
+                let txn = TransactionId { session: i_session, index: i_txn };
+                let mut info = TransactionInfo {
+                    attempt: transaction.attempt,
+                    ..Default::default()
+                };
+                let mut own_writes = HashMap::new();
+
+                for (i_event, event) in transaction.events.iter().enumerate() {
+                    if !event.success {
+                        continue;
+                    }
+                    let (variable, value) = (event.variable, event.value);
+                    if event.write {
+                        own_writes.insert(variable, value);
+                        info.writes.insert(variable);
+                    } else if let Some(&expected) = own_writes.get(&variable) {
+                        if expected != value {
+                            anomalies.push(Violation::InternalRead { txn, variable, expected, value });
+                        }
+                    } else {
+                        let source = if value == 0 {
+                            Ok(None)
+                        } else {
+                            match write_index.get(&(variable, value)) {
+                                None => Err(Violation::ThinAirRead { txn, variable, value }),
+                                Some(write) if write.txn == txn => {
+                                    Err(Violation::FutureRead { txn, variable, value })
+                                }
+                                Some(write) if !write.committed => Err(Violation::AbortedRead {
+                                    txn,
+                                    writer: write.txn,
+                                    variable,
+                                    value,
+                                }),
+                                Some(write) if !write.last => Err(Violation::IntermediateRead {
+                                    txn,
+                                    writer: write.txn,
+                                    variable,
+                                    value,
+                                }),
+                                Some(write) => Ok(Some(write.txn)),
+                            }
+                        };
+                        match source {
+                            Ok(source) => info.reads.push(ExternalRead { event: i_event, variable, source }),
+                            Err(violation) => anomalies.push(violation),
+                        }
+                    }
+                }
+
+                for &variable in info.writes.iter() {
+                    writers.entry(variable).or_default().push(txn);
+                }
+                infos.insert(txn, info);
+                committed.push(txn);
+            }
+            sessions.push(committed);
+        }
+
+        (TransactionalHistory { sessions, infos, writers }, anomalies)
+    }
+
+    /// The last transaction of every other session that commits before `txn` starts.
+    ///
+    /// Together with session order these transactions precede `txn` in real time.
+    /// Transactions without recorded timing are unordered in real time.
+    pub fn real_time_predecessors(&self, txn: TransactionId) -> Vec<TransactionId> {
+        let start = match self.infos[&txn].attempt {
+            Some(attempt) => attempt.start.monotonic,
+            None => return Vec::new(),
+        };
+        self.sessions
+            .iter()
+            .enumerate()
+            .filter(|&(i_session, _)| i_session != txn.session)
+            .filter_map(|(_, session)| {
+                session
+                    .iter()
+                    .rev()
+                    .find(|other| {
+                        let attempt = self.infos[other].attempt;
+                        attempt.is_some_and(|attempt| attempt.end.monotonic < start)
+                    })
+                    .copied()
+            })
+            .collect()
+    }
+
+    /// The initial state followed by all committed transactions.
+    pub fn nodes(&self) -> Vec<Source> {
+        let mut nodes = vec![None];
+        nodes.extend(self.sessions.iter().flatten().map(|&txn| Some(txn)));
+        nodes
+    }
+}
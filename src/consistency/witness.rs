@@ -0,0 +1,175 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::db::history::{Event, Session};
+
+use super::graph::{Edge, EdgeKind};
+use super::util::{Source, TransactionId, Variable};
+use super::{Consistency, Violation};
+
+/// An event of a history, identified by its transaction and its position in it.
+#[derive(Clone, Debug)]
+pub struct EventRef {
+    pub txn: TransactionId,
+    pub index: usize,
+    pub event: Event,
+}
+
+/// An edge of a violating cycle with the events that create it.
+#[derive(Clone, Debug)]
+pub struct EdgeWitness {
+    pub edge: Edge,
+    pub events: Vec<EventRef>,
+}
+
+/// The cycles explaining why a history violates a consistency level.
+#[derive(Clone, Debug)]
+pub struct Witness {
+    pub level: Consistency,
+    pub cycles: Vec<Vec<EdgeWitness>>,
+}
+
+fn events<'a>(histories: &'a [Session], txn: &TransactionId) -> impl Iterator<Item = (usize, &'a Event)> {
+    histories[txn.session][txn.index]
+        .events
+        .iter()
+        .enumerate()
+        .filter(|(_, event)| event.success)
+}
+
+fn last_write(histories: &[Session], txn: &Source, variable: Variable) -> Option<EventRef> {
+    let txn = (*txn)?;
+    events(histories, &txn)
+        .filter(|(_, event)| event.write && event.variable == variable)
+        .last()
+        .map(|(index, event)| EventRef { txn, index, event: event.clone() })
+}
+
+/// The first read of `variable` before the transaction writes it, returning `value` if given.
+fn external_read(histories: &[Session], txn: &Source, variable: Variable, value: Option<usize>) -> Option<EventRef> {
+    let txn = (*txn)?;
+    events(histories, &txn)
+        .filter(|(_, event)| event.variable == variable)
+        .take_while(|(_, event)| !event.write)
+        .find(|(_, event)| value.is_none() || value == Some(event.value))
+        .map(|(index, event)| EventRef { txn, index, event: event.clone() })
+}
+
+impl EdgeWitness {
+    fn new(edge: &Edge, histories: &[Session]) -> Self {
+        let events = match edge.kind {
+            EdgeKind::SessionOrder | EdgeKind::RealTime => vec![],
+            EdgeKind::WriteRead(x) => {
+                let write = last_write(histories, &edge.from, x);
+                let value = write.as_ref().map_or(0, |write| write.event.value);
+                vec![write, external_read(histories, &edge.to, x, Some(value))]
+            }
+            EdgeKind::WriteWrite { variable: x, reader } => {
+                let write = last_write(histories, &edge.to, x);
+                let value = write.as_ref().map_or(0, |write| write.event.value);
+                let read = reader.and_then(|reader| external_read(histories, &Some(reader), x, Some(value)));
+                vec![last_write(histories, &edge.from, x), write, read]
+            }
+            EdgeKind::ReadWrite(x) => {
+                vec![external_read(histories, &edge.from, x, None), last_write(histories, &edge.to, x)]
+            }
+        };
+        EdgeWitness {
+            edge: *edge,
+            events: events.into_iter().flatten().collect(),
+        }
+    }
+}
+
+fn node_repr(node: &Source) -> String {
+    match node {
+        Some(txn) => txn.to_string(),
+        None => "init".to_string(),
+    }
+}
+
+impl Witness {
+    /// The witness of a violation, if the violation is explained by cycles.
+    pub fn new(violation: &Violation, histories: &[Session]) -> Option<Self> {
+        let (level, cycles) = match violation {
+            Violation::Cycle { level, edges } => (*level, vec![edges.clone()]),
+            Violation::NoCommitOrder { level, cycles } if !cycles.is_empty() => (*level, cycles.clone()),
+            _ => return None,
+        };
+        Some(Witness {
+            level,
+            cycles: cycles
+                .iter()
+                .map(|edges| edges.iter().map(|edge| EdgeWitness::new(edge, histories)).collect())
+                .collect(),
+        })
+    }
+
+    /// Renders the cycles as a Graphviz digraph, labelling every transaction with its events.
+    pub fn to_dot(&self, histories: &[Session]) -> String {
+        let nodes: BTreeSet<Source> = self
+            .cycles
+            .iter()
+            .flatten()
+            .flat_map(|witness| vec![witness.edge.from, witness.edge.to])
+            .collect();
+
+        let mut dot = format!("digraph \"{:?}\" {{\n", self.level);
+        dot += "  node [shape=box, fontname=monospace];\n";
+        for node in nodes.iter() {
+            let label = match node {
+                Some(txn) => {
+                    let events: Vec<String> = histories[txn.session][txn.index]
+                        .events
+                        .iter()
+                        .map(|event| format!("{:?}", event))
+                        .collect();
+                    format!("{}\\n{}", txn, events.join("\\n"))
+                }
+                None => "init".to_string(),
+            };
+            dot += &format!("  \"{}\" [label=\"{}\"];\n", node_repr(node), label);
+        }
+        for (i_cycle, cycle) in self.cycles.iter().enumerate() {
+            for witness in cycle.iter() {
+                let label = if self.cycles.len() > 1 {
+                    format!("{} ({})", witness.edge.kind, i_cycle + 1)
+                } else {
+                    witness.edge.kind.to_string()
+                };
+                dot += &format!(
+                    "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    node_repr(&witness.edge.from),
+                    node_repr(&witness.edge.to),
+                    label
+                );
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+impl fmt::Display for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i_cycle, cycle) in self.cycles.iter().enumerate() {
+            if self.cycles.len() > 1 {
+                writeln!(f, "cycle {}:", i_cycle + 1)?;
+            }
+            for witness in cycle.iter() {
+                write!(
+                    f,
+                    "  {} -{}-> {}",
+                    node_repr(&witness.edge.from),
+                    witness.edge.kind,
+                    node_repr(&witness.edge.to)
+                )?;
+                for event in witness.events.iter() {
+                    write!(f, "  {}[{}]{:?}", event.txn, event.index, event.event)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
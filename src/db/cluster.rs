@@ -1,4 +1,8 @@
 use super::history::{HistParams, History, Session};
+use super::error::DbcopError;
+use super::format::{read_history, write_history, Encoding};
+use super::placement::Placement;
+use super::retry::RetryPolicy;
 // use verifier::Verifier;
 
 // use std::collections::HashMap;
@@ -18,7 +22,7 @@ use std::time::Duration;
 // use std::convert::From;
 
 // use serde_yaml;
-use log::{info, warn};
+use log::{error, info, warn};
 
 #[derive(Debug, Clone)]
 pub struct Node {
@@ -27,7 +31,8 @@ pub struct Node {
 }
 
 pub trait ClusterNode {
-    fn exec_session(&self, hist: &mut Session);
+    /// Executes the transactions of a session, replacing them with the attempts made.
+    fn exec_session(&self, hist: &mut Session, retry: &RetryPolicy) -> Result<(), DbcopError>;
 }
 
 pub trait Cluster<N>
@@ -35,36 +40,54 @@ where
     N: 'static + Send + ClusterNode,
 {
     fn n_node(&self) -> usize;
-    fn setup(&self) -> bool;
-    fn setup_test(&mut self, p: &HistParams);
+    fn setup(&self) -> Result<(), DbcopError>;
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError>;
     fn get_node(&self, id: usize) -> Node;
     fn get_cluster_node(&self, id: usize) -> N;
-    fn cleanup(&self);
+    fn cleanup(&self) -> Result<(), DbcopError>;
     fn info(&self) -> String;
 
-    fn node_vec(ips: &[&str]) -> Vec<Node> where Self: Sized {
+    fn node_vec(ips: &[&str]) -> Result<Vec<Node>, DbcopError> where Self: Sized {
         ips.iter()
             .enumerate()
-            .map(|(i, ip)| Node {
-                addr: ip.parse().unwrap(),
-                id: i + 1,
+            .map(|(i, ip)| match ip.parse() {
+                Ok(addr) => Ok(Node { addr, id: i + 1 }),
+                Err(e) => Err(DbcopError::Connection(format!("invalid address {}: {}", ip, e))),
             })
             .collect()
     }
 
-    fn execute_all(&mut self, r_dir: &Path, o_dir: &Path, millisec: u64) -> Option<usize> {
+    /// Executes every history of `r_dir`, returning how many were executed. A history that
+    /// cannot be read or fails is reported and skipped, the output directory of a failed one removed.
+    fn execute_all(
+        &mut self,
+        r_dir: &Path,
+        o_dir: &Path,
+        millisec: u64,
+        retry: &RetryPolicy,
+        placement: &Placement,
+    ) -> Result<usize, DbcopError> {
         info!("Reading all histories from {:?}", r_dir);
-        let histories: Vec<History> = fs::read_dir(r_dir)
-            .unwrap()
-            .filter_map(|entry_res| match entry_res {
-                Ok(ref entry) if !&entry.path().is_dir() => {
-                    let file = File::open(entry.path()).unwrap();
-                    let buf_reader = BufReader::new(file);
-                    Some(bincode::deserialize_from(buf_reader).unwrap())
+        let mut histories: Vec<History> = Vec::new();
+        let entries = fs::read_dir(r_dir).map_err(|e| DbcopError::Serialization(e.to_string()))?;
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    error!("Reading an entry of {:?} failed: {}", r_dir, e);
+                    continue;
                 }
-                _ => None,
-            })
-            .collect();
+            };
+            if !path.is_dir() {
+                let history = File::open(&path)
+                    .map_err(|e| DbcopError::Serialization(e.to_string()))
+                    .and_then(|file| read_history(BufReader::new(file)));
+                match history {
+                    Ok(history) => histories.push(history),
+                    Err(e) => error!("Reading history {:?} failed: {}", path, e),
+                }
+            }
+        }
 
         info!("Successfully reading {} histories", histories.len());
     
@@ -75,8 +98,16 @@ where
             info!("Create output directory of {:?}", curr_dir);
             if fs::create_dir(&curr_dir).is_ok() {
                 info!("Created successfully! Ready to execute this history");
-                self.execute(history, &curr_dir);
-                executed_count += 1;
+                match self.execute(history, &curr_dir, retry, placement) {
+                    Ok(()) => executed_count += 1,
+                    Err(e) => {
+                        error!("Executing history {} failed: {}", history.get_id(), e);
+                        // so that the history is executed again by the next run
+                        if let Err(e) = fs::remove_dir_all(&curr_dir) {
+                            error!("Removing {:?} failed: {}", curr_dir, e);
+                        }
+                    }
+                }
                 sleep(Duration::from_millis(millisec));
             } else {
                 warn!("The output directory is not empty, so skipping");
@@ -85,60 +116,103 @@ where
 
         info!("Successfully Executed {} histories", executed_count);
 
-        None
+        Ok(executed_count)
     }
 
-    fn execute(&mut self, hist: &History, dir: &Path) -> Option<usize> {
+    fn execute(&mut self, hist: &History, dir: &Path, retry: &RetryPolicy, placement: &Placement) -> Result<(), DbcopError> {
+        let session_nodes = placement.assign(hist.get_data().len(), self.n_node(), hist.get_params().get_seed())?;
+
         info!("Step-1: setup");
-        self.setup();
+        self.setup()?;
 
         info!("Step-2: setup-test");
-        self.setup_test(hist.get_params());
+        if let Err(e) = self.setup_test(hist.get_params()) {
+            if let Err(cleanup) = self.cleanup() {
+                error!("Cleaning up after the failed setup failed: {}", cleanup);
+            }
+            return Err(e);
+        }
 
         let mut exec = hist.get_cloned_data();
 
         let start_time = chrono::Local::now();
 
         info!("Step-3: exec-history");
-        self.exec_history(&mut exec);
+        let executed = self.exec_history(&mut exec, &session_nodes, retry);
 
         let end_time = chrono::Local::now();
 
         info!("Step-4: clean up");
-        self.cleanup();
+        let cleaned = self.cleanup();
+        if let Err(e) = executed {
+            if let Err(cleanup) = cleaned {
+                error!("Cleaning up after the failed execution failed: {}", cleanup);
+            }
+            return Err(e);
+        }
+        cleaned?;
 
         info!("Step-5: write out");
-        let exec_hist = History::new(
+        let mut exec_hist = History::new(
             hist.get_cloned_params(),
             self.info(),
             start_time,
             end_time,
             exec,
         );
+        exec_hist.set_session_nodes(session_nodes);
 
-        let file = File::create(dir.join("history.bincode")).unwrap();
+        let file = File::create(dir.join("history.bincode")).map_err(|e| DbcopError::Serialization(e.to_string()))?;
         let buf_writer = BufWriter::new(file);
-        bincode::serialize_into(buf_writer, &exec_hist).expect("dumping to bincode went wrong");
-
-        None
+        write_history(buf_writer, &exec_hist, Encoding::Bincode)
     }
 
-    fn exec_history(&self, hist: &mut Vec<Session>) {
-        let mut threads = (0..self.n_node())
-            .cycle()
-            .zip(hist.drain(..))
-            .enumerate()
-            .map(|(index, (node_id, mut single_hist))| {
-                let cluster_node = self.get_cluster_node(node_id);
-                let session_name = format!("session-{}", index);
-                thread::Builder::new()
-                    .name(session_name)
-                    .spawn(move || {
-                        cluster_node.exec_session(&mut single_hist);
-                        single_hist
-                    }).unwrap()
-            })
-            .collect::<Vec<_>>();
-        hist.extend(threads.drain(..).map(|t| t.join().unwrap()));
+    /// Executes every session in its own thread, connected to the node of index
+    /// `session_nodes[i]` for session `i`. All sessions run to the end even if some fail, the
+    /// first failure is returned. If a session cannot be started, the ones already started are
+    /// still waited for.
+    fn exec_history(&self, hist: &mut Vec<Session>, session_nodes: &[usize], retry: &RetryPolicy) -> Result<(), DbcopError> {
+        // the nodes of all sessions are created before any session starts
+        let cluster_nodes: Vec<N> = session_nodes.iter().map(|&node_id| self.get_cluster_node(node_id)).collect();
+        let mut threads = Vec::new();
+        let mut spawned = Ok(());
+        for (index, (cluster_node, mut single_hist)) in cluster_nodes.into_iter().zip(hist.drain(..)).enumerate() {
+            let session_name = format!("session-{}", index);
+            let retry = *retry;
+            let thread = thread::Builder::new()
+                .name(session_name)
+                .spawn(move || {
+                    let result = cluster_node.exec_session(&mut single_hist, &retry);
+                    (single_hist, result)
+                });
+            match thread {
+                Ok(thread) => threads.push(thread),
+                Err(e) => {
+                    let e = DbcopError::Execution(format!("session {} could not start: {}", index, e));
+                    error!("{}", e);
+                    spawned = Err(e);
+                    break;
+                }
+            }
+        }
+
+        let mut result = spawned;
+        for (index, thread) in threads.drain(..).enumerate() {
+            match thread.join() {
+                Ok((single_hist, session_result)) => {
+                    hist.push(single_hist);
+                    if let Err(e) = session_result {
+                        error!("Session {} failed: {}", index, e);
+                        result = result.and(Err(e));
+                    }
+                }
+                Err(_) => {
+                    let e = DbcopError::Execution(format!("session {} panicked", index));
+                    error!("{}", e);
+                    result = result.and(Err(e));
+                }
+            }
+        }
+        result
     }
 }
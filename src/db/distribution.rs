@@ -1,17 +1,34 @@
 extern crate rand;
 
-use rand::prelude::{Distribution, ThreadRng};
+use rand::distributions::{Bernoulli, Uniform};
+use rand::prelude::Distribution;
+use rand::{Rng, RngCore};
+
+use serde::{Deserialize, Serialize};
+
+use zipf::ZipfDistribution;
 
 pub struct MyDistribution<D: Distribution<usize>> {
     d: D,
 }
 
 pub trait MyDistributionTrait {
-    fn sample(&self, rng: &mut ThreadRng) -> usize;
+    fn sample(&mut self, rng: &mut dyn RngCore) -> usize;
+
+    /// The variable of a write, drawn like the variable of a read unless overridden.
+    fn sample_write(&mut self, rng: &mut dyn RngCore) -> usize {
+        self.sample(rng)
+    }
+
+    /// Called before generating the transactions of `session`.
+    fn start_session(&mut self, _session: usize) {}
+
+    /// Called after generating a write of `variable`.
+    fn written(&mut self, _variable: usize) {}
 }
 
 impl<D: Distribution<usize>> MyDistributionTrait for MyDistribution<D> {
-    fn sample(&self, rng: &mut ThreadRng) -> usize {
+    fn sample(&mut self, rng: &mut dyn RngCore) -> usize {
         self.d.sample(rng)
     }
 }
@@ -21,3 +38,201 @@ impl<D: Distribution<usize>> MyDistribution<D> {
         MyDistribution { d }
     }
 }
+
+pub struct HotspotDistribution {
+    hot_probability: Bernoulli,
+    hot_key: Uniform<usize>,
+    non_hot_key: Uniform<usize>,
+}
+
+impl Distribution<usize> for HotspotDistribution {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        if self.hot_probability.sample(rng) {
+            self.hot_key.sample(rng)
+        } else {
+            self.non_hot_key.sample(rng)
+        }
+    }
+}
+
+impl HotspotDistribution {
+    pub fn new(n_variables: usize) -> HotspotDistribution {
+        HotspotDistribution::with_hotspot(n_variables, 0.8, 0.2)
+    }
+
+    /// Accesses the first `hot_fraction` of the variables with probability `hot_probability`.
+    /// There is at least one hot and one other variable, given at least two variables, a single
+    /// variable being both.
+    pub fn with_hotspot(n_variables: usize, hot_probability: f64, hot_fraction: f64) -> HotspotDistribution {
+        let hot_key_max = ((n_variables as f64 * hot_fraction) as usize).clamp(1, n_variables.max(2) - 1);
+        HotspotDistribution {
+            hot_probability: Bernoulli::new(hot_probability).unwrap(),
+            hot_key: Uniform::new(0, hot_key_max),
+            non_hot_key: Uniform::new(hot_key_max.min(n_variables - 1), n_variables),
+        }
+    }
+}
+
+/// Reads the recently written variables more often, their recency rank following a Zipf
+/// distribution. Writes and reads before any write are uniform.
+pub struct LatestDistribution {
+    exponent: f64,
+    uniform: Uniform<usize>,
+    // written variables, the most recent last
+    recent: Vec<usize>,
+}
+
+impl LatestDistribution {
+    pub fn new(n_variables: usize, exponent: f64) -> LatestDistribution {
+        LatestDistribution {
+            exponent,
+            uniform: Uniform::new(0, n_variables),
+            recent: Vec::new(),
+        }
+    }
+}
+
+impl MyDistributionTrait for LatestDistribution {
+    fn sample(&mut self, rng: &mut dyn RngCore) -> usize {
+        if self.recent.is_empty() {
+            return self.uniform.sample(rng);
+        }
+        let rank = ZipfDistribution::new(self.recent.len(), self.exponent)
+            .unwrap()
+            .sample(rng);
+        self.recent[self.recent.len() - rank]
+    }
+
+    fn sample_write(&mut self, rng: &mut dyn RngCore) -> usize {
+        self.uniform.sample(rng)
+    }
+
+    fn written(&mut self, variable: usize) {
+        self.recent.retain(|&x| x != variable);
+        self.recent.push(variable);
+    }
+}
+
+/// Accesses the variables with exponentially decreasing probability, the variable being
+/// `n_variables` times an exponential sample of rate `rate`, resampled until it is in range.
+/// The rate must be positive.
+pub struct ExponentialDistribution {
+    n_variables: usize,
+    rate: f64,
+}
+
+impl ExponentialDistribution {
+    pub fn new(n_variables: usize, rate: f64) -> ExponentialDistribution {
+        ExponentialDistribution { n_variables, rate }
+    }
+}
+
+impl Distribution<usize> for ExponentialDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        loop {
+            // 1 - u is in (0, 1]
+            let u: f64 = rng.gen();
+            let x = -(1.0 - u).ln() / self.rate;
+            let variable = (x * self.n_variables as f64) as usize;
+            if variable < self.n_variables {
+                return variable;
+            }
+        }
+    }
+}
+
+/// Accesses the variables in order, wrapping around after the last one.
+pub struct SequentialDistribution {
+    n_variables: usize,
+    next: usize,
+}
+
+impl SequentialDistribution {
+    pub fn new(n_variables: usize) -> SequentialDistribution {
+        SequentialDistribution { n_variables, next: 0 }
+    }
+}
+
+impl MyDistributionTrait for SequentialDistribution {
+    fn sample(&mut self, _rng: &mut dyn RngCore) -> usize {
+        let variable = self.next;
+        self.next = (self.next + 1) % self.n_variables;
+        variable
+    }
+}
+
+/// Splits the variables between the sessions, each session writing only the variables of its
+/// own partition. Reads are uniform over all variables.
+pub struct PartitionedDistribution {
+    n_variables: usize,
+    n_sessions: usize,
+    all: Uniform<usize>,
+    partition: Uniform<usize>,
+}
+
+impl PartitionedDistribution {
+    pub fn new(n_variables: usize, n_sessions: usize) -> PartitionedDistribution {
+        let mut distribution = PartitionedDistribution {
+            n_variables,
+            n_sessions,
+            all: Uniform::new(0, n_variables),
+            partition: Uniform::new(0, n_variables),
+        };
+        distribution.start_session(0);
+        distribution
+    }
+}
+
+impl MyDistributionTrait for PartitionedDistribution {
+    fn sample(&mut self, rng: &mut dyn RngCore) -> usize {
+        self.all.sample(rng)
+    }
+
+    fn sample_write(&mut self, rng: &mut dyn RngCore) -> usize {
+        self.partition.sample(rng)
+    }
+
+    fn start_session(&mut self, session: usize) {
+        // a history without sessions never draws from its partitions
+        let n_sessions = self.n_sessions.max(1);
+        let i = session * self.n_variables / n_sessions;
+        // with fewer variables than sessions, partitions of a single variable are shared
+        let j = std::cmp::max((session + 1) * self.n_variables / n_sessions, i + 1);
+        self.partition = Uniform::new(i, j);
+    }
+}
+
+/// A key distribution with its parameters, as recorded in generated histories.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum KeyDistribution {
+    Uniform,
+    Zipf { exponent: f64 },
+    Hotspot { hot_probability: f64, hot_fraction: f64 },
+    Latest { exponent: f64 },
+    Exponential { rate: f64 },
+    Sequential,
+    Partitioned,
+}
+
+impl KeyDistribution {
+    /// The distribution over `n_variables` variables accessed by `n_sessions` sessions.
+    pub fn sampler(&self, n_variables: usize, n_sessions: usize) -> Box<dyn MyDistributionTrait> {
+        match *self {
+            KeyDistribution::Uniform => Box::new(MyDistribution::new(Uniform::new(0, n_variables))),
+            KeyDistribution::Zipf { exponent } => Box::new(MyDistribution::new(
+                ZipfDistribution::new(n_variables, exponent)
+                    .unwrap()
+                    .map(|x| x - 1),
+            )),
+            KeyDistribution::Hotspot { hot_probability, hot_fraction } => Box::new(MyDistribution::new(
+                HotspotDistribution::with_hotspot(n_variables, hot_probability, hot_fraction),
+            )),
+            KeyDistribution::Latest { exponent } => Box::new(LatestDistribution::new(n_variables, exponent)),
+            KeyDistribution::Exponential { rate } => Box::new(MyDistribution::new(
+                ExponentialDistribution::new(n_variables, rate),
+            )),
+            KeyDistribution::Sequential => Box::new(SequentialDistribution::new(n_variables)),
+            KeyDistribution::Partitioned => Box::new(PartitionedDistribution::new(n_variables, n_sessions)),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::error::Error;
+use std::fmt;
+
+/// Failures while running histories on a database.
+#[derive(Debug)]
+pub enum DbcopError {
+    /// A node cannot be reached or its address is invalid.
+    Connection(String),
+    /// Creating or dropping the database or its variables failed.
+    Setup(String),
+    /// A session could not be executed to the end.
+    Execution(String),
+    /// A history could not be read or written.
+    Serialization(String),
+}
+
+impl fmt::Display for DbcopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbcopError::Connection(msg) => write!(f, "connection error: {}", msg),
+            DbcopError::Setup(msg) => write!(f, "setup error: {}", msg),
+            DbcopError::Execution(msg) => write!(f, "execution error: {}", msg),
+            DbcopError::Serialization(msg) => write!(f, "serialization error: {}", msg),
+        }
+    }
+}
+
+impl Error for DbcopError {}
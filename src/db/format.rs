@@ -0,0 +1,375 @@
+use std::io::{Read, Write};
+
+use bincode::Options;
+use serde::de::DeserializeOwned;
+
+use super::error::DbcopError;
+use super::history::{History, HistoryV1, HistoryV2, HistoryV3};
+
+/// Start of a history file, followed by the format version as a little-endian `u32` and the
+/// encoding of the rest of the file as one byte.
+pub const MAGIC: &[u8; 8] = b"DBCOPHST";
+
+/// Version of the layout of `History` written by this version of dbcop.
+///
+/// - 1: sessions of transactions with their events, and the size of the history.
+/// - 2: attempts with their timestamps and abort reasons, and all generation parameters.
+/// - 3: the number of sessions, and the node each session was executed on.
+/// - 4: the workload.
+pub const VERSION: u32 = 4;
+
+const HEADER_LEN: usize = MAGIC.len() + 4 + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Bincode = 0,
+    Json = 1,
+}
+
+impl TryFrom<u8> for Encoding {
+    type Error = DbcopError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0 => Ok(Encoding::Bincode),
+            1 => Ok(Encoding::Json),
+            _ => Err(DbcopError::Serialization(format!("unknown history encoding {}", byte))),
+        }
+    }
+}
+
+fn serialization<E: ToString>(e: E) -> DbcopError {
+    DbcopError::Serialization(e.to_string())
+}
+
+// the layout of `bincode::serialize`, strict so that one version cannot be mistaken for another
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+}
+
+/// Writes `history` with a header of the current version.
+pub fn write_history<W: Write>(mut writer: W, history: &History, encoding: Encoding) -> Result<(), DbcopError> {
+    writer.write_all(MAGIC).map_err(serialization)?;
+    writer.write_all(&VERSION.to_le_bytes()).map_err(serialization)?;
+    writer.write_all(&[encoding as u8]).map_err(serialization)?;
+    match encoding {
+        Encoding::Bincode => bincode_options().serialize_into(writer, history).map_err(serialization),
+        Encoding::Json => serde_json::to_writer_pretty(writer, history).map_err(serialization),
+    }
+}
+
+/// Reads a history of any version, migrating it to the current one.
+///
+/// Files without a header are read as plain JSON, or as bincode of any version.
+pub fn read_history<R: Read>(mut reader: R) -> Result<History, DbcopError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(serialization)?;
+
+    if bytes.starts_with(MAGIC) {
+        if bytes.len() < HEADER_LEN {
+            return Err(DbcopError::Serialization("truncated history header".to_string()));
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[MAGIC.len()..MAGIC.len() + 4]);
+        let encoding = Encoding::try_from(bytes[HEADER_LEN - 1])?;
+        decode(u32::from_le_bytes(version), encoding, &bytes[HEADER_LEN..])
+    } else {
+        // the newest version that decodes, the strict options make an older layout unlikely to
+        // decode as a newer one
+        let bincode = || {
+            (1..VERSION)
+                .rev()
+                .fold(decode(VERSION, Encoding::Bincode, &bytes), |result, version| {
+                    result.or_else(|e| decode(version, Encoding::Bincode, &bytes).map_err(|_| e))
+                })
+        };
+        if bytes.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{') {
+            // fields added since version 1 have defaults in JSON
+            decode(VERSION, Encoding::Json, &bytes).or_else(|_| bincode())
+        } else {
+            bincode()
+        }
+    }
+}
+
+fn decode(version: u32, encoding: Encoding, payload: &[u8]) -> Result<History, DbcopError> {
+    match version {
+        1 => deserialize::<HistoryV1>(encoding, payload).map(History::from),
+        2 => deserialize::<HistoryV2>(encoding, payload).map(History::from),
+        3 => deserialize::<HistoryV3>(encoding, payload).map(History::from),
+        VERSION => deserialize(encoding, payload),
+        _ => Err(DbcopError::Serialization(format!("unsupported history format version {}", version))),
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(encoding: Encoding, payload: &[u8]) -> Result<T, DbcopError> {
+    match encoding {
+        Encoding::Bincode => bincode_options().deserialize(payload).map_err(serialization),
+        Encoding::Json => serde_json::from_slice(payload).map_err(serialization),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Local};
+    use serde::Serialize;
+
+    use super::*;
+    use crate::db::distribution::KeyDistribution;
+    use crate::db::history::{generate_mult_histories, Event, HistoryParams, Session};
+    use crate::db::workload::Workload;
+
+    // the layouts of the older versions, as they were written
+
+    #[derive(Serialize)]
+    struct HistParamsV1 {
+        id: usize,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        n_event: usize,
+    }
+
+    #[derive(Serialize)]
+    struct TransactionV1 {
+        events: Vec<Event>,
+        success: bool,
+    }
+
+    #[derive(Serialize)]
+    struct HistoryV1 {
+        params: HistParamsV1,
+        info: String,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        data: Vec<Vec<TransactionV1>>,
+    }
+
+    #[derive(Serialize)]
+    struct HistParamsV2 {
+        id: usize,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        n_event: usize,
+        seed: Option<u64>,
+        read_probability: Option<f64>,
+        key_distribution: Option<KeyDistribution>,
+        longtxn_proportion: Option<f64>,
+        longtxn_size: Option<f64>,
+        random_txn_size: Option<bool>,
+    }
+
+    #[derive(Serialize)]
+    struct HistoryV2<'a> {
+        params: HistParamsV2,
+        info: String,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        data: &'a Vec<Session>,
+    }
+
+    #[derive(Serialize)]
+    struct HistParamsV3 {
+        id: usize,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        n_event: usize,
+        n_session: Option<usize>,
+        seed: Option<u64>,
+        read_probability: Option<f64>,
+        key_distribution: Option<KeyDistribution>,
+        longtxn_proportion: Option<f64>,
+        longtxn_size: Option<f64>,
+        random_txn_size: Option<bool>,
+    }
+
+    #[derive(Serialize)]
+    struct HistoryV3<'a> {
+        params: HistParamsV3,
+        info: String,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        data: &'a Vec<Session>,
+        session_nodes: Vec<usize>,
+    }
+
+    fn generated(seed: u64) -> History {
+        generate_mult_histories(HistoryParams {
+            n_hist: 1,
+            n_node: 3,
+            n_session: 3,
+            n_variable: 5,
+            n_transaction: 3,
+            n_event: 2,
+            read_probability: 0.3,
+            longtxn_proportion: 0.0,
+            longtxn_size: 10.0,
+            key_distribution: KeyDistribution::Zipf { exponent: 0.5 },
+            workload: Workload::Random,
+            random_txn_size: false,
+            seed,
+        })
+        .pop()
+        .unwrap()
+    }
+
+    fn with_header(version: u32, encoding: Encoding, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.push(encoding as u8);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    fn v2(history: &History) -> HistoryV2<'_> {
+        let params = history.get_params();
+        HistoryV2 {
+            params: HistParamsV2 {
+                id: 4,
+                n_node: params.get_n_node(),
+                n_variable: params.get_n_variable(),
+                n_transaction: params.get_n_transaction(),
+                n_event: params.get_event(),
+                seed: Some(9),
+                read_probability: Some(0.3),
+                key_distribution: None,
+                longtxn_proportion: None,
+                longtxn_size: None,
+                random_txn_size: None,
+            },
+            info: "PostgreSQL".to_string(),
+            start: history.get_start(),
+            end: history.get_end(),
+            data: history.get_data(),
+        }
+    }
+
+    fn v3(history: &History) -> HistoryV3<'_> {
+        let params = history.get_params();
+        HistoryV3 {
+            params: HistParamsV3 {
+                id: 5,
+                n_node: params.get_n_node(),
+                n_variable: params.get_n_variable(),
+                n_transaction: params.get_n_transaction(),
+                n_event: params.get_event(),
+                n_session: Some(3),
+                seed: Some(9),
+                read_probability: Some(0.3),
+                key_distribution: params.get_key_distribution(),
+                longtxn_proportion: None,
+                longtxn_size: None,
+                random_txn_size: None,
+            },
+            info: "PostgreSQL".to_string(),
+            start: history.get_start(),
+            end: history.get_end(),
+            data: history.get_data(),
+            session_nodes: vec![1, 0, 1],
+        }
+    }
+
+    #[test]
+    fn current_version_round_trips() {
+        let mut history = generated(1);
+        history.set_session_nodes(vec![0, 1, 0]);
+        for encoding in [Encoding::Bincode, Encoding::Json] {
+            let mut bytes = Vec::new();
+            write_history(&mut bytes, &history, encoding).unwrap();
+            assert!(bytes.starts_with(MAGIC));
+            let read = read_history(&bytes[..]).unwrap();
+            assert_eq!(read.get_data(), history.get_data());
+            assert_eq!(read.get_session_nodes(), &vec![0, 1, 0]);
+            assert_eq!(read.get_params().get_workload(), Some(Workload::Random));
+        }
+    }
+
+    #[test]
+    fn headerless_v1_bincode_is_migrated() {
+        let now = Local::now();
+        let v1 = HistoryV1 {
+            params: HistParamsV1 { id: 3, n_node: 1, n_variable: 2, n_transaction: 1, n_event: 2 },
+            info: "PostgreSQL".to_string(),
+            start: now,
+            end: now,
+            data: vec![vec![TransactionV1 {
+                events: vec![Event::write(0, 1), Event::read(1)],
+                success: true,
+            }]],
+        };
+        let history = read_history(&bincode::serialize(&v1).unwrap()[..]).unwrap();
+        assert_eq!((history.get_id(), history.get_info()), (3, "PostgreSQL"));
+        assert_eq!(history.get_params().get_read_probability(), None);
+        assert_eq!(history.get_params().get_workload(), None);
+        let transaction = &history.get_data()[0][0];
+        assert!(transaction.success && transaction.attempt.is_none() && !transaction.gave_up);
+        assert_eq!(transaction.events, vec![Event::write(0, 1), Event::read(1)]);
+    }
+
+    #[test]
+    fn headered_older_payloads_are_migrated() {
+        let generated = generated(2);
+
+        let bincode = with_header(2, Encoding::Bincode, &bincode::serialize(&v2(&generated)).unwrap());
+        let json = with_header(2, Encoding::Json, &serde_json::to_vec(&v2(&generated)).unwrap());
+        for bytes in [bincode, json] {
+            let history = read_history(&bytes[..]).unwrap();
+            assert_eq!((history.get_id(), history.get_data()), (4, generated.get_data()));
+            assert_eq!(history.get_params().get_seed(), Some(9));
+            // there was one session per node
+            assert_eq!(history.get_params().get_n_session(), 3);
+            assert_eq!(history.get_params().get_workload(), Some(Workload::Random));
+        }
+
+        let bincode = with_header(3, Encoding::Bincode, &bincode::serialize(&v3(&generated)).unwrap());
+        let json = with_header(3, Encoding::Json, &serde_json::to_vec(&v3(&generated)).unwrap());
+        for bytes in [bincode, json] {
+            let history = read_history(&bytes[..]).unwrap();
+            assert_eq!((history.get_id(), history.get_data()), (5, generated.get_data()));
+            assert_eq!(history.get_session_nodes(), &vec![1, 0, 1]);
+            assert_eq!(history.get_params().get_workload(), Some(Workload::Random));
+        }
+    }
+
+    #[test]
+    fn headerless_json_is_read() {
+        let generated = generated(3);
+        let history = read_history(&serde_json::to_vec_pretty(&generated).unwrap()[..]).unwrap();
+        assert_eq!((history.get_id(), history.get_data()), (generated.get_id(), generated.get_data()));
+
+        // fields added since are defaulted
+        let history = read_history(&serde_json::to_vec(&v2(&generated)).unwrap()[..]).unwrap();
+        assert_eq!((history.get_id(), history.get_data()), (4, generated.get_data()));
+        assert!(history.get_session_nodes().is_empty());
+    }
+
+    #[test]
+    fn headerless_bincode_is_read_as_the_newest_version_that_decodes() {
+        let generated = generated(4);
+
+        let history = read_history(&bincode::serialize(&v3(&generated)).unwrap()[..]).unwrap();
+        assert_eq!((history.get_id(), history.get_session_nodes()), (5, &vec![1, 0, 1]));
+        let history = read_history(&bincode::serialize(&v2(&generated)).unwrap()[..]).unwrap();
+        assert_eq!((history.get_id(), history.get_data()), (4, generated.get_data()));
+
+        // an id whose first byte looks like the start of JSON
+        let mut bytes = bincode::serialize(&generated).unwrap();
+        bytes[0] = b'{';
+        let history = read_history(&bytes[..]).unwrap();
+        assert_eq!((history.get_id(), history.get_data()), (usize::from(b'{'), generated.get_data()));
+        assert_eq!(history.get_params().get_workload(), Some(Workload::Random));
+    }
+
+    #[test]
+    fn unknown_versions_and_encodings_are_errors() {
+        assert!(read_history(&with_header(VERSION + 1, Encoding::Bincode, b"")[..]).is_err());
+        let mut bytes = with_header(VERSION, Encoding::Bincode, b"");
+        bytes[HEADER_LEN - 1] = 2;
+        assert!(read_history(&bytes[..]).is_err());
+        assert!(read_history(&MAGIC[..]).is_err());
+    }
+}
@@ -1,10 +1,17 @@
 use std::fmt;
 
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Instant;
 
 use rand::distributions::{Distribution, Bernoulli, Uniform};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 
-use super::distribution::MyDistributionTrait;
+use super::distribution::KeyDistribution;
+use super::retry::RetryPolicy;
+use super::workload::{Access, Workload};
 
 use chrono::{DateTime, Duration, Local};
 
@@ -22,22 +29,111 @@ pub struct Event {
 pub struct Transaction {
     pub events: Vec<Event>,
     pub success: bool,
+    /// When the transaction was attempted, if it was executed.
+    #[serde(default)]
+    pub attempt: Option<Attempt>,
+    /// Why the attempt aborted.
+    #[serde(default)]
+    pub abort: Option<Abort>,
+    /// The transaction was not attempted again after this aborted attempt.
+    #[serde(default)]
+    pub gave_up: bool,
+}
+
+/// An instant as wall-clock time and as monotonic time since the process started.
+///
+/// Only monotonic times taken in the same run of dbcop are comparable.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Timestamp {
+    pub wall: DateTime<Local>,
+    /// Nanoseconds since the first timestamp of the process.
+    pub monotonic: u64,
+}
+
+impl Timestamp {
+    pub fn now() -> Self {
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        let epoch = *EPOCH.get_or_init(Instant::now);
+        Timestamp {
+            wall: Local::now(),
+            monotonic: epoch.elapsed().as_nanos() as u64,
+        }
+    }
+}
+
+/// An attempt at executing a transaction, from its start to its commit or abort.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Attempt {
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+/// The kinds of failure that abort a transaction attempt.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug, Hash)]
+pub enum AbortKind {
+    /// The database could not serialize the transaction, SQLSTATE 40001.
+    SerializationFailure,
+    Deadlock,
+    LockWaitTimeout,
+    /// A Galera node rejected the write set of the transaction at commit.
+    CertificationFailure,
+    /// A concurrent transaction committed a conflicting write first.
+    CommitConflict,
+    ConnectionLoss,
+    Other,
+}
+
+impl fmt::Display for AbortKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AbortKind::SerializationFailure => "serialization failure",
+            AbortKind::Deadlock => "deadlock",
+            AbortKind::LockWaitTimeout => "lock wait timeout",
+            AbortKind::CertificationFailure => "certification failure",
+            AbortKind::CommitConflict => "commit conflict",
+            AbortKind::ConnectionLoss => "connection loss",
+            AbortKind::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Why a transaction attempt aborted, as reported by the database.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+pub struct Abort {
+    pub kind: AbortKind,
+    /// The error code of the database, such as a SQLSTATE.
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Abort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{} [{}]: {}", self.kind, code, self.message),
+            None => write!(f, "{}: {}", self.kind, self.message),
+        }
+    }
 }
 
 pub type Session = Vec<Transaction>;
 
 #[derive(Clone, Copy)]
-pub struct HistoryParams<'a> {
+pub struct HistoryParams {
     pub n_hist: usize,
     pub n_node: usize,
+    pub n_session: usize,
     pub n_variable: usize,
     pub n_transaction: usize,
     pub n_event: usize,
     pub read_probability: f64,
     pub longtxn_proportion: f64,
     pub longtxn_size: f64,
-    pub key_distribution: &'a dyn MyDistributionTrait,
+    pub key_distribution: KeyDistribution,
+    pub workload: Workload,
     pub random_txn_size: bool,
+    /// Seed of the first history, the following ones are seeded with its successors.
+    pub seed: u64,
 }
 
 impl fmt::Debug for Event {
@@ -74,6 +170,76 @@ impl Event {
     }
 }
 
+/// Written values keep the generated value in the low half of their bits and the number of
+/// earlier attempts at the transaction in the high half, so that every attempt writes values of
+/// its own. Halving the width keeps the shifts in range on 32-bit targets.
+const ATTEMPT_SHIFT: u32 = usize::BITS / 2;
+
+/// The attempts made at executing the transactions of a session, each as its own entry.
+pub struct SessionRecorder {
+    retry: RetryPolicy,
+    executed: Session,
+    /// Aborted attempts at the current transaction.
+    aborted: usize,
+}
+
+impl SessionRecorder {
+    pub fn new(retry: &RetryPolicy) -> Self {
+        SessionRecorder {
+            retry: *retry,
+            executed: Vec::new(),
+            aborted: 0,
+        }
+    }
+
+    /// Whether to attempt `transaction`, waiting for the backoff delay before a retry. When the
+    /// retry policy gives up, the last attempt is marked as such.
+    pub fn next_attempt(&mut self, transaction: &Transaction) -> bool {
+        if transaction.success {
+            self.aborted = 0;
+            false
+        } else if self.aborted == 0 {
+            true
+        } else if self.retry.retries(self.aborted) {
+            thread::sleep(self.retry.delay(self.aborted));
+            true
+        } else {
+            if let Some(attempt) = self.executed.last_mut() {
+                attempt.gave_up = true;
+            }
+            self.aborted = 0;
+            false
+        }
+    }
+
+    /// Records the attempt at `transaction` started at `start` and ending now. If it aborted,
+    /// `transaction` is reset so that it can be attempted again, writing values distinct from
+    /// the ones of the aborted attempts.
+    pub fn end_attempt(&mut self, transaction: &mut Transaction, start: Timestamp) {
+        transaction.attempt = Some(Attempt { start, end: Timestamp::now() });
+        self.executed.push(transaction.clone());
+        if !transaction.success {
+            self.aborted += 1;
+            for event in transaction.events.iter_mut() {
+                event.success = false;
+                if event.write {
+                    let generated = event.value & ((1 << ATTEMPT_SHIFT) - 1);
+                    event.value = generated | (self.aborted << ATTEMPT_SHIFT);
+                } else {
+                    event.value = 0;
+                }
+            }
+            transaction.attempt = None;
+            transaction.abort = None;
+        }
+    }
+
+    /// Replaces the transactions of the session with the recorded attempts.
+    pub fn finish(self, hist: &mut Session) {
+        *hist = self.executed;
+    }
+}
+
 impl fmt::Debug for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let repr = format!("{:?}", self.events);
@@ -91,6 +257,25 @@ pub struct HistParams {
     n_variable: usize,
     n_transaction: usize,
     n_event: usize,
+    /// Number of sessions, `n_node` for histories generated before they were decoupled.
+    #[serde(default)]
+    n_session: Option<usize>,
+    /// Seed the history was generated from, if it was generated with one.
+    #[serde(default)]
+    seed: Option<u64>,
+    // the remaining generation parameters, unknown for histories generated before they were recorded
+    #[serde(default)]
+    read_probability: Option<f64>,
+    #[serde(default)]
+    key_distribution: Option<KeyDistribution>,
+    #[serde(default)]
+    longtxn_proportion: Option<f64>,
+    #[serde(default)]
+    longtxn_size: Option<f64>,
+    #[serde(default)]
+    random_txn_size: Option<bool>,
+    #[serde(default)]
+    workload: Option<Workload>,
 }
 
 impl HistParams {
@@ -103,6 +288,9 @@ impl HistParams {
     pub fn get_n_node(&self) -> usize {
         self.n_node
     }
+    pub fn get_n_session(&self) -> usize {
+        self.n_session.unwrap_or(self.n_node)
+    }
     pub fn get_n_variable(&self) -> usize {
         self.n_variable
     }
@@ -112,6 +300,27 @@ impl HistParams {
     pub fn get_event(&self) -> usize {
         self.n_event
     }
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+    pub fn get_read_probability(&self) -> Option<f64> {
+        self.read_probability
+    }
+    pub fn get_key_distribution(&self) -> Option<KeyDistribution> {
+        self.key_distribution
+    }
+    pub fn get_longtxn_proportion(&self) -> Option<f64> {
+        self.longtxn_proportion
+    }
+    pub fn get_longtxn_size(&self) -> Option<f64> {
+        self.longtxn_size
+    }
+    pub fn get_random_txn_size(&self) -> Option<bool> {
+        self.random_txn_size
+    }
+    pub fn get_workload(&self) -> Option<Workload> {
+        self.workload
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -121,6 +330,9 @@ pub struct History {
     start: DateTime<Local>,
     end: DateTime<Local>,
     data: Vec<Session>,
+    /// Index of the node each session was executed on, empty if the history was not executed.
+    #[serde(default)]
+    session_nodes: Vec<usize>,
 }
 
 impl History {
@@ -137,6 +349,7 @@ impl History {
             start,
             end,
             data,
+            session_nodes: Vec::new(),
         }
     }
 
@@ -144,10 +357,22 @@ impl History {
         self.params.get_id()
     }
 
+    pub fn get_info(&self) -> &str {
+        &self.info
+    }
+
     pub fn get_data(&self) -> &Vec<Session> {
         &self.data
     }
 
+    pub fn get_session_nodes(&self) -> &Vec<usize> {
+        &self.session_nodes
+    }
+
+    pub fn set_session_nodes(&mut self, session_nodes: Vec<usize>) {
+        self.session_nodes = session_nodes;
+    }
+
     pub fn get_cloned_data(&self) -> Vec<Session> {
         self.data.clone()
     }
@@ -160,26 +385,194 @@ impl History {
         self.params.clone()
     }
 
+    pub fn get_start(&self) -> DateTime<Local> {
+        self.start
+    }
+
+    pub fn get_end(&self) -> DateTime<Local> {
+        self.end
+    }
+
     pub fn get_duration(&self) -> Duration {
         self.end - self.start
     }
 }
 
+// Version 1 of the history format, written before attempts, abort reasons and generation
+// parameters were recorded.
+
+#[derive(Deserialize)]
+struct HistParamsV1 {
+    id: usize,
+    n_node: usize,
+    n_variable: usize,
+    n_transaction: usize,
+    n_event: usize,
+}
+
+#[derive(Deserialize)]
+struct TransactionV1 {
+    events: Vec<Event>,
+    success: bool,
+}
+
+#[derive(Deserialize)]
+pub(super) struct HistoryV1 {
+    params: HistParamsV1,
+    info: String,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    data: Vec<Vec<TransactionV1>>,
+}
+
+impl From<HistoryV1> for History {
+    fn from(v1: HistoryV1) -> Self {
+        let params = HistParams {
+            id: v1.params.id,
+            n_node: v1.params.n_node,
+            n_variable: v1.params.n_variable,
+            n_transaction: v1.params.n_transaction,
+            n_event: v1.params.n_event,
+            ..Default::default()
+        };
+        let data = v1
+            .data
+            .into_iter()
+            .map(|session| {
+                session
+                    .into_iter()
+                    .map(|transaction| Transaction {
+                        events: transaction.events,
+                        success: transaction.success,
+                        attempt: None,
+                        abort: None,
+                        gave_up: false,
+                    })
+                    .collect()
+            })
+            .collect();
+        History::new(params, v1.info, v1.start, v1.end, data)
+    }
+}
+
+// Version 2 of the history format, written before the number of sessions and the node of each
+// session were recorded.
+
+#[derive(Deserialize)]
+struct HistParamsV2 {
+    id: usize,
+    n_node: usize,
+    n_variable: usize,
+    n_transaction: usize,
+    n_event: usize,
+    seed: Option<u64>,
+    read_probability: Option<f64>,
+    key_distribution: Option<KeyDistribution>,
+    longtxn_proportion: Option<f64>,
+    longtxn_size: Option<f64>,
+    random_txn_size: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct HistoryV2 {
+    params: HistParamsV2,
+    info: String,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    data: Vec<Session>,
+}
+
+impl From<HistoryV2> for History {
+    fn from(v2: HistoryV2) -> Self {
+        let params = HistParams {
+            id: v2.params.id,
+            n_node: v2.params.n_node,
+            n_session: None,
+            n_variable: v2.params.n_variable,
+            n_transaction: v2.params.n_transaction,
+            n_event: v2.params.n_event,
+            seed: v2.params.seed,
+            read_probability: v2.params.read_probability,
+            key_distribution: v2.params.key_distribution,
+            longtxn_proportion: v2.params.longtxn_proportion,
+            longtxn_size: v2.params.longtxn_size,
+            random_txn_size: v2.params.random_txn_size,
+            // histories were only generated by the random workload
+            workload: v2.params.seed.map(|_| Workload::Random),
+        };
+        History::new(params, v2.info, v2.start, v2.end, v2.data)
+    }
+}
+
+// Version 3 of the history format, written before the workload was recorded.
+
+#[derive(Deserialize)]
+struct HistParamsV3 {
+    id: usize,
+    n_node: usize,
+    n_variable: usize,
+    n_transaction: usize,
+    n_event: usize,
+    n_session: Option<usize>,
+    seed: Option<u64>,
+    read_probability: Option<f64>,
+    key_distribution: Option<KeyDistribution>,
+    longtxn_proportion: Option<f64>,
+    longtxn_size: Option<f64>,
+    random_txn_size: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct HistoryV3 {
+    params: HistParamsV3,
+    info: String,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    data: Vec<Session>,
+    session_nodes: Vec<usize>,
+}
+
+impl From<HistoryV3> for History {
+    fn from(v3: HistoryV3) -> Self {
+        let params = HistParams {
+            id: v3.params.id,
+            n_node: v3.params.n_node,
+            n_session: v3.params.n_session,
+            n_variable: v3.params.n_variable,
+            n_transaction: v3.params.n_transaction,
+            n_event: v3.params.n_event,
+            seed: v3.params.seed,
+            read_probability: v3.params.read_probability,
+            key_distribution: v3.params.key_distribution,
+            longtxn_proportion: v3.params.longtxn_proportion,
+            longtxn_size: v3.params.longtxn_size,
+            random_txn_size: v3.params.random_txn_size,
+            // histories were only generated by the random workload
+            workload: v3.params.seed.map(|_| Workload::Random),
+        };
+        let mut history = History::new(params, v3.info, v3.start, v3.end, v3.data);
+        history.set_session_nodes(v3.session_nodes);
+        history
+    }
+}
+
+/// Generates the sessions of a history, the same parameters and seed always give the same
+/// sessions.
 pub fn generate_single_history(
     params: HistoryParams
 ) -> Vec<Session> {
     let mut counters = HashMap::new();
-    let mut random_generator = rand::thread_rng();
+    let mut random_generator = ChaCha8Rng::seed_from_u64(params.seed);
+    let mut key_distribution = params.key_distribution.sampler(params.n_variable, params.n_session);
     let read_distribution = Bernoulli::new(params.read_probability).unwrap();
     let longtxn_distribution = Bernoulli::new(params.longtxn_proportion).unwrap();
-    let shorttxn_size_distribution = Uniform::new(1, 2 * params.n_event - 1);
-    let longtxn_size_distribution = Uniform::new((params.n_event as f64 * params.longtxn_size * 0.5) as usize,
-                                                 (params.n_event as f64 * params.longtxn_size * 1.5) as usize);
-    // let _jump = (params.n_variable as f64 / params.n_node as f64).ceil() as usize;
-    (0..params.n_node).map(|_| {
-        // let i = i_node * jump;
-        // let j = std::cmp::min((i_node + 1) * jump, n_variable);
-        // let write_variable_range = Uniform::from(i..j);
+    // transactions of a single event are never resized
+    let shorttxn_size_distribution = Uniform::new(1, (2 * params.n_event).max(3) - 1);
+    let longtxn_size_min = (params.n_event as f64 * params.longtxn_size * 0.5) as usize;
+    let longtxn_size_max = (params.n_event as f64 * params.longtxn_size * 1.5) as usize;
+    let longtxn_size_distribution = Uniform::new(longtxn_size_min, longtxn_size_max.max(longtxn_size_min + 1));
+    (0..params.n_session).map(|i_session| {
+        key_distribution.start_session(i_session);
         (0..params.n_transaction).map(|_| {
             let size = if params.random_txn_size {
                 if longtxn_distribution.sample(&mut random_generator) {
@@ -195,13 +588,16 @@ pub fn generate_single_history(
                 }
             };
 
-            let generate_event = |_| {
-                if read_distribution.sample(&mut random_generator) {
-                    let variable = params.key_distribution.sample(&mut random_generator);
-                    Event::read(variable)
-                } else {
-                    let variable = params.key_distribution.sample(&mut random_generator);
-                    // let variable = write_variable_range.sample(&mut random_generator);
+            let accesses = params.workload.transaction(
+                size,
+                &read_distribution,
+                key_distribution.as_mut(),
+                params.n_variable,
+                &mut random_generator,
+            );
+            let generate_event = |access| match access {
+                Access::Read(variable) => Event::read(variable),
+                Access::Write(variable) => {
                     let value = {
                         let entry = counters.entry(variable).or_insert(0);
                         *entry += 1;
@@ -212,8 +608,11 @@ pub fn generate_single_history(
             };
 
             Transaction {
-                events: (0..size).map(generate_event).collect(),
+                events: accesses.into_iter().map(generate_event).collect(),
                 success: false,
+                attempt: None,
+                abort: None,
+                gave_up: false,
             }
         }).collect()
     }).collect()
@@ -223,23 +622,155 @@ pub fn generate_mult_histories(
     params: HistoryParams
 ) -> Vec<History> {
     (0..params.n_hist).map(|i_hist| -> History {
+        let seed = params.seed.wrapping_add(i_hist as u64);
         let start_time = Local::now();
         let hist = generate_single_history(
-            params
+            HistoryParams { seed, ..params }
         );
         let end_time = Local::now();
         History {
             params: HistParams {
                 id: i_hist,
                 n_node: params.n_node,
+                n_session: Some(params.n_session),
                 n_variable: params.n_variable,
                 n_transaction: params.n_transaction,
                 n_event: params.n_event,
+                seed: Some(seed),
+                read_probability: Some(params.read_probability),
+                // the TPC-C transactions do not draw their variables from it
+                key_distribution: Some(params.key_distribution).filter(|_| params.workload != Workload::Tpcc),
+                longtxn_proportion: Some(params.longtxn_proportion),
+                longtxn_size: Some(params.longtxn_size),
+                random_txn_size: Some(params.random_txn_size),
+                workload: Some(params.workload),
             },
             info: "generated".to_string(),
             start: start_time,
             end: end_time,
             data: hist,
+            session_nodes: Vec::new(),
         }
     }).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(events: Vec<Event>) -> Transaction {
+        Transaction { events, success: false, attempt: None, abort: None, gave_up: false }
+    }
+
+    /// Executes `session`, attempt `n` at transaction `i` committing if `commits(i, n)`.
+    fn execute(session: &mut Session, max_attempts: Option<usize>, commits: impl Fn(usize, usize) -> bool) {
+        let retry = RetryPolicy { max_attempts, delay: std::time::Duration::ZERO, ..Default::default() };
+        let mut recorder = SessionRecorder::new(&retry);
+        for (i, transaction) in session.iter_mut().enumerate() {
+            let mut n = 0;
+            while recorder.next_attempt(transaction) {
+                let start = Timestamp::now();
+                transaction.success = commits(i, n);
+                for event in transaction.events.iter_mut() {
+                    event.success = true;
+                }
+                recorder.end_attempt(transaction, start);
+                n += 1;
+            }
+        }
+        recorder.finish(session);
+    }
+
+    fn params(key_distribution: KeyDistribution) -> HistoryParams {
+        HistoryParams {
+            n_hist: 1,
+            n_node: 2,
+            n_session: 3,
+            n_variable: 4,
+            n_transaction: 5,
+            n_event: 3,
+            read_probability: 0.5,
+            longtxn_proportion: 0.2,
+            longtxn_size: 3.0,
+            key_distribution,
+            workload: Workload::Random,
+            random_txn_size: false,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn smallest_parameters_generate_histories() {
+        let single_event = HistoryParams {
+            n_event: 1,
+            random_txn_size: true,
+            longtxn_size: 0.5,
+            ..params(KeyDistribution::Uniform)
+        };
+        assert!(generate_single_history(single_event)
+            .iter()
+            .flatten()
+            .all(|transaction| transaction.events.len() <= 1));
+
+        let hotspot = KeyDistribution::Hotspot { hot_probability: 0.8, hot_fraction: 0.2 };
+        let single_variable = HistoryParams { n_variable: 1, ..params(hotspot) };
+        assert!(generate_single_history(single_variable)
+            .iter()
+            .flatten()
+            .flat_map(|transaction| transaction.events.iter())
+            .all(|event| event.variable == 0));
+
+        let no_session = HistoryParams { n_session: 0, ..params(KeyDistribution::Partitioned) };
+        assert!(generate_single_history(no_session).is_empty());
+    }
+
+    #[test]
+    fn last_attempt_is_marked_given_up() {
+        let mut session = vec![pending(vec![Event::write(0, 1)]), pending(vec![Event::write(0, 2)])];
+        // the first transaction always aborts, the second one commits at its second attempt
+        execute(&mut session, Some(3), |i, n| i == 1 && n == 1);
+        let outcomes: Vec<_> = session.iter().map(|t| (t.success, t.gave_up)).collect();
+        assert_eq!(
+            outcomes,
+            vec![(false, false), (false, false), (false, true), (false, false), (true, false)]
+        );
+        assert!(session.iter().all(|t| t.attempt.is_some()));
+    }
+
+    #[test]
+    fn attempts_write_values_of_their_own() {
+        let mut session = vec![pending(vec![Event::read(0), Event::write(0, 1)])];
+        execute(&mut session, Some(3), |_, _| false);
+        let values: Vec<_> = session.iter().map(|t| t.events[1].value).collect();
+        assert_eq!(values, vec![1, 1 | 1 << ATTEMPT_SHIFT, 1 | 2 << ATTEMPT_SHIFT]);
+    }
+
+    #[test]
+    fn no_limit_retries_until_the_commit() {
+        let mut session = vec![pending(vec![Event::write(0, 1)])];
+        execute(&mut session, None, |_, n| n == 20);
+        assert_eq!(session.len(), 21);
+        assert!(session.last().unwrap().success);
+        assert!(session.iter().all(|t| !t.gave_up));
+    }
+
+    #[test]
+    fn same_seed_generates_the_same_sessions() {
+        for workload in [Workload::Random, Workload::YcsbF, Workload::Tpcc] {
+            let same = HistoryParams {
+                workload,
+                random_txn_size: true,
+                ..params(KeyDistribution::Latest { exponent: 1.0 })
+            };
+            assert!(generate_single_history(same) == generate_single_history(same));
+            let other = HistoryParams { seed: 8, ..same };
+            assert!(generate_single_history(same) != generate_single_history(other));
+        }
+
+        // history i of a batch is generated with the seed of the batch plus i
+        let batch = generate_mult_histories(HistoryParams { n_hist: 3, ..params(KeyDistribution::Uniform) });
+        let third = generate_single_history(HistoryParams { seed: 9, ..params(KeyDistribution::Uniform) });
+        assert!(batch[2].get_data() == &third);
+        assert_eq!(batch[2].get_params().get_seed(), Some(9));
+    }
+}
@@ -1,3 +1,8 @@
 pub mod cluster;
 pub mod history;
 pub mod distribution;
+pub mod error;
+pub mod format;
+pub mod placement;
+pub mod retry;
+pub mod workload;
@@ -0,0 +1,96 @@
+use clap::ValueEnum;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::error::DbcopError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PlacementPolicy {
+    /// The sessions on the nodes in turn.
+    RoundRobin,
+    /// Every session on a node drawn at random, seeded by the seed of the history.
+    Random,
+    /// The sessions on the given nodes in turn.
+    Pinned,
+    /// Every session on the first given node.
+    Single,
+}
+
+/// How the sessions of a history are assigned to the nodes of a cluster.
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub policy: PlacementPolicy,
+    /// Indices of the nodes used by the `Pinned` and `Single` policies, node 0 if empty.
+    pub nodes: Vec<usize>,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement {
+            policy: PlacementPolicy::RoundRobin,
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl Placement {
+    /// The index of the node of each of `n_session` sessions on a cluster of `n_node` nodes. A
+    /// random placement is drawn from `seed`, from entropy if the history has none.
+    pub fn assign(&self, n_session: usize, n_node: usize, seed: Option<u64>) -> Result<Vec<usize>, DbcopError> {
+        if n_node == 0 {
+            return Err(DbcopError::Setup("the cluster has no node".to_string()));
+        }
+        if let Some(node) = self.nodes.iter().find(|&&node| node >= n_node) {
+            return Err(DbcopError::Setup(format!("node {} is not in the cluster of {} nodes", node, n_node)));
+        }
+        let nodes = if self.nodes.is_empty() { vec![0] } else { self.nodes.clone() };
+        let assignment = match self.policy {
+            PlacementPolicy::RoundRobin => (0..n_session).map(|session| session % n_node).collect(),
+            PlacementPolicy::Random => {
+                let mut rng = match seed {
+                    Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+                    None => ChaCha8Rng::from_entropy(),
+                };
+                (0..n_session).map(|_| rng.gen_range(0..n_node)).collect()
+            }
+            PlacementPolicy::Pinned => (0..n_session).map(|session| nodes[session % nodes.len()]).collect(),
+            PlacementPolicy::Single => vec![nodes[0]; n_session],
+        };
+        Ok(assignment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assign(policy: PlacementPolicy, nodes: Vec<usize>) -> Result<Vec<usize>, DbcopError> {
+        Placement { policy, nodes }.assign(5, 3, Some(7))
+    }
+
+    #[test]
+    fn sessions_are_assigned_in_turn() {
+        assert_eq!(assign(PlacementPolicy::RoundRobin, vec![]).unwrap(), vec![0, 1, 2, 0, 1]);
+        assert_eq!(assign(PlacementPolicy::Pinned, vec![2, 1]).unwrap(), vec![2, 1, 2, 1, 2]);
+        assert_eq!(assign(PlacementPolicy::Pinned, vec![]).unwrap(), vec![0; 5]);
+    }
+
+    #[test]
+    fn single_placement_uses_the_first_node() {
+        assert_eq!(assign(PlacementPolicy::Single, vec![]).unwrap(), vec![0; 5]);
+        assert_eq!(assign(PlacementPolicy::Single, vec![2, 1]).unwrap(), vec![2; 5]);
+    }
+
+    #[test]
+    fn random_placement_follows_the_seed() {
+        let placement = assign(PlacementPolicy::Random, vec![]).unwrap();
+        assert!(placement.iter().all(|&node| node < 3));
+        assert_eq!(assign(PlacementPolicy::Random, vec![]).unwrap(), placement);
+    }
+
+    #[test]
+    fn nodes_outside_the_cluster_are_errors() {
+        assert!(assign(PlacementPolicy::Pinned, vec![1, 3]).is_err());
+        assert!(Placement::default().assign(5, 0, None).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::cmp::min;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use rand::Rng;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backoff {
+    /// The same delay before every retry.
+    Fixed,
+    /// The delay doubles with every retry.
+    Exponential,
+    /// A random delay up to the exponential one.
+    Jittered,
+}
+
+/// How often and how eagerly an aborted transaction is attempted again.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Attempts at a transaction before giving up on it, `None` for no limit.
+    pub max_attempts: Option<usize>,
+    pub backoff: Backoff,
+    /// Delay before the first retry.
+    pub delay: Duration,
+    /// Bound on the delay before any retry.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: Some(10),
+            backoff: Backoff::Exponential,
+            delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The limit on attempts given as a count, 0 standing for no limit.
+    pub fn attempt_limit(max_attempts: usize) -> Option<usize> {
+        (max_attempts > 0).then_some(max_attempts)
+    }
+
+    /// Whether a transaction that aborted `attempts` times is attempted again.
+    pub fn retries(&self, attempts: usize) -> bool {
+        match self.max_attempts {
+            Some(max_attempts) => attempts < max_attempts,
+            None => true,
+        }
+    }
+
+    /// The delay before attempting a transaction that aborted `attempts` times.
+    pub fn delay(&self, attempts: usize) -> Duration {
+        let exponential = || {
+            let factor = 1u32.checked_shl(attempts.saturating_sub(1) as u32).unwrap_or(u32::MAX);
+            min(self.delay.saturating_mul(factor), self.max_delay)
+        };
+        match self.backoff {
+            Backoff::Fixed => min(self.delay, self.max_delay),
+            Backoff::Exponential => exponential(),
+            Backoff::Jittered => exponential().mul_f64(rand::thread_rng().gen::<f64>()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(backoff: Backoff) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: Some(3),
+            backoff,
+            delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn fixed_delay_is_the_same_before_every_retry() {
+        let fixed = policy(Backoff::Fixed);
+        assert!([1, 2, 10].iter().all(|&attempts| fixed.delay(attempts) == Duration::from_millis(10)));
+
+        let clamped = RetryPolicy { max_delay: Duration::from_millis(4), ..fixed };
+        assert_eq!(clamped.delay(1), Duration::from_millis(4));
+    }
+
+    #[test]
+    fn exponential_delay_doubles_up_to_the_cap() {
+        let exponential = policy(Backoff::Exponential);
+        let delays: Vec<_> = (1..=5).map(|attempts| exponential.delay(attempts).as_millis()).collect();
+        assert_eq!(delays, vec![10, 20, 40, 50, 50]);
+        // the factor saturates rather than overflows
+        assert_eq!(exponential.delay(usize::MAX), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn jittered_delay_is_at_most_the_exponential_one() {
+        let jittered = policy(Backoff::Jittered);
+        let exponential = policy(Backoff::Exponential);
+        for attempts in 1..=5 {
+            assert!(jittered.delay(attempts) <= exponential.delay(attempts));
+        }
+    }
+
+    #[test]
+    fn zero_max_attempts_means_no_limit() {
+        assert_eq!(RetryPolicy::attempt_limit(0), None);
+        assert_eq!(RetryPolicy::attempt_limit(3), Some(3));
+
+        let unlimited = RetryPolicy { max_attempts: RetryPolicy::attempt_limit(0), ..policy(Backoff::Fixed) };
+        assert!(unlimited.retries(1) && unlimited.retries(usize::MAX));
+        let limited = policy(Backoff::Fixed);
+        assert!(limited.retries(2) && !limited.retries(3));
+    }
+}
@@ -0,0 +1,216 @@
+use std::ops::Range;
+
+use clap::ValueEnum;
+use rand::distributions::{Bernoulli, Distribution};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::distribution::{KeyDistribution, MyDistributionTrait};
+
+/// Longest scan of the YCSB E workload.
+const MAX_SCAN_LENGTH: usize = 10;
+
+/// The shape of the generated transactions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+pub enum Workload {
+    /// Reads with the read probability, writes otherwise.
+    Random,
+    /// YCSB A: 50% reads, 50% updates.
+    YcsbA,
+    /// YCSB B: 95% reads, 5% updates.
+    YcsbB,
+    /// YCSB C: reads only.
+    YcsbC,
+    /// YCSB D: 95% reads of recently written variables, 5% inserts.
+    YcsbD,
+    /// YCSB E: 95% scans of consecutive variables, 5% inserts.
+    YcsbE,
+    /// YCSB F: 50% reads, 50% read-modify-writes.
+    YcsbF,
+    /// A simplified TPC-C mix of new orders, payments, order statuses, deliveries and stock levels.
+    Tpcc,
+}
+
+/// An access of a generated transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read(usize),
+    Write(usize),
+}
+
+enum Operation {
+    Read,
+    Update,
+    Insert,
+    ReadModifyWrite,
+    Scan,
+}
+
+impl Workload {
+    /// The key distribution of the workload when none is given.
+    pub fn key_distribution(&self) -> KeyDistribution {
+        match self {
+            Workload::Random | Workload::Tpcc => KeyDistribution::Uniform,
+            Workload::YcsbD => KeyDistribution::Latest { exponent: 0.99 },
+            _ => KeyDistribution::Zipf { exponent: 0.99 },
+        }
+    }
+
+    /// The accesses of a transaction of `size` operations. The read probability is only used by
+    /// the random workload, and the TPC-C transactions have their own size and pick the variables
+    /// of each table uniformly.
+    pub fn transaction(
+        &self,
+        size: usize,
+        read_distribution: &Bernoulli,
+        keys: &mut dyn MyDistributionTrait,
+        n_variable: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<Access> {
+        if *self == Workload::Tpcc {
+            return tpcc_transaction(n_variable, rng);
+        }
+
+        let mut accesses = Vec::new();
+        for _ in 0..size {
+            match self.operation(read_distribution, rng) {
+                Operation::Read => accesses.push(Access::Read(keys.sample(rng))),
+                Operation::Update | Operation::Insert => {
+                    let variable = keys.sample_write(rng);
+                    keys.written(variable);
+                    accesses.push(Access::Write(variable));
+                }
+                Operation::ReadModifyWrite => {
+                    let variable = keys.sample_write(rng);
+                    keys.written(variable);
+                    accesses.push(Access::Read(variable));
+                    accesses.push(Access::Write(variable));
+                }
+                Operation::Scan => {
+                    let start = keys.sample(rng);
+                    let length = rng.gen_range(1..=MAX_SCAN_LENGTH.min(n_variable));
+                    accesses.extend((start..start + length).map(|variable| Access::Read(variable % n_variable)));
+                }
+            }
+        }
+        accesses
+    }
+
+    fn operation(&self, read_distribution: &Bernoulli, rng: &mut dyn RngCore) -> Operation {
+        let percent = |rng: &mut dyn RngCore, p: u32| rng.gen_range(0..100) < p;
+        match self {
+            Workload::Random => {
+                if read_distribution.sample(rng) {
+                    Operation::Read
+                } else {
+                    Operation::Update
+                }
+            }
+            Workload::YcsbA => if percent(rng, 50) { Operation::Read } else { Operation::Update },
+            Workload::YcsbB => if percent(rng, 95) { Operation::Read } else { Operation::Update },
+            Workload::YcsbC => Operation::Read,
+            Workload::YcsbD => if percent(rng, 95) { Operation::Read } else { Operation::Insert },
+            Workload::YcsbE => if percent(rng, 95) { Operation::Scan } else { Operation::Insert },
+            Workload::YcsbF => if percent(rng, 50) { Operation::Read } else { Operation::ReadModifyWrite },
+            Workload::Tpcc => unreachable!("TPC-C transactions are not made of operations"),
+        }
+    }
+}
+
+/// The variables split into the tables of TPC-C, all tables sharing every variable if there are
+/// fewer than four.
+struct Tables {
+    warehouse: Range<usize>,
+    district: Range<usize>,
+    customer: Range<usize>,
+    stock: Range<usize>,
+}
+
+impl Tables {
+    fn new(n_variable: usize) -> Self {
+        if n_variable < 4 {
+            return Tables {
+                warehouse: 0..n_variable,
+                district: 0..n_variable,
+                customer: 0..n_variable,
+                stock: 0..n_variable,
+            };
+        }
+        let warehouse = (n_variable / 100).max(1);
+        let district = warehouse + (n_variable / 10).max(1);
+        let customer = district + (n_variable * 3 / 10).max(1);
+        Tables {
+            warehouse: 0..warehouse,
+            district: warehouse..district,
+            customer: district..customer,
+            stock: customer..n_variable,
+        }
+    }
+}
+
+fn read_modify_write(accesses: &mut Vec<Access>, variable: usize) {
+    accesses.push(Access::Read(variable));
+    accesses.push(Access::Write(variable));
+}
+
+fn tpcc_transaction(n_variable: usize, rng: &mut dyn RngCore) -> Vec<Access> {
+    let tables = Tables::new(n_variable);
+    let mut pick = |table: &Range<usize>| rng.gen_range(table.clone());
+    let mut accesses = Vec::new();
+
+    let kind = pick(&(0..100));
+    if kind < 45 {
+        // new order: the district hands out the order id, every item updates its stock
+        accesses.push(Access::Read(pick(&tables.warehouse)));
+        read_modify_write(&mut accesses, pick(&tables.district));
+        accesses.push(Access::Read(pick(&tables.customer)));
+        for _ in 0..pick(&(5..16)) {
+            read_modify_write(&mut accesses, pick(&tables.stock));
+        }
+    } else if kind < 88 {
+        // payment
+        read_modify_write(&mut accesses, pick(&tables.warehouse));
+        read_modify_write(&mut accesses, pick(&tables.district));
+        read_modify_write(&mut accesses, pick(&tables.customer));
+    } else if kind < 92 {
+        // order status
+        accesses.push(Access::Read(pick(&tables.customer)));
+        accesses.push(Access::Read(pick(&tables.district)));
+    } else if kind < 96 {
+        // delivery
+        for _ in 0..10 {
+            read_modify_write(&mut accesses, pick(&tables.district));
+            read_modify_write(&mut accesses, pick(&tables.customer));
+        }
+    } else {
+        // stock level
+        accesses.push(Access::Read(pick(&tables.district)));
+        for _ in 0..pick(&(10..21)) {
+            accesses.push(Access::Read(pick(&tables.stock)));
+        }
+    }
+    accesses
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn read_modify_writes_stay_in_the_partition_of_the_session() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let reads = Bernoulli::new(0.5).unwrap();
+        let mut keys = KeyDistribution::Partitioned.sampler(8, 4);
+        keys.start_session(1);
+        for _ in 0..100 {
+            for access in Workload::YcsbF.transaction(4, &reads, keys.as_mut(), 8, &mut rng) {
+                if let Access::Write(variable) = access {
+                    assert!((2..4).contains(&variable), "session 1 wrote variable {}", variable);
+                }
+            }
+        }
+    }
+}
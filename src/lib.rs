@@ -0,0 +1,4 @@
+pub mod clients;
+pub mod consistency;
+pub mod db;
+pub mod verifier;
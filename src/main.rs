@@ -1,54 +1,40 @@
-mod clients;
-mod db;
-
 use clap::{Parser, Subcommand, ValueEnum};
-use clients::{DynCluster, DynNode, PostgresCluster, PostgresSERCluster, DGraphCluster, GaleraCluster, MySQLCluster, TDSQLCluster};
-use db::cluster::Cluster;
+use dbcop::clients::{DynCluster, DynNode, PostgresCluster, PostgresSERCluster, DGraphCluster, GaleraCluster, MySQLCluster, TDSQLCluster, SimulatedCluster, SimulatedIsolation, SqliteBegin, SqliteCluster};
+use dbcop::clients::{TiDBCluster, TiDBTxnMode, YugabyteCluster, YugabyteSERCluster};
+#[cfg(feature = "antidotedb")]
+use dbcop::clients::AntidoteCluster;
+#[cfg(feature = "cockroachdb")]
+use dbcop::clients::CockroachCluster;
+#[cfg(feature = "memgraph")]
+use dbcop::clients::MemgraphCluster;
+use dbcop::consistency::adya;
+use dbcop::consistency::session::{check_session_guarantee, SessionGuarantee};
+use dbcop::consistency::util::{TransactionId, TransactionalHistory};
+use dbcop::consistency::witness::Witness;
+use dbcop::consistency::Consistency;
+use dbcop::db::cluster::Cluster;
+use dbcop::verifier::Verifier;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
-
-use rand::distributions::{Bernoulli, Distribution, Uniform};
+use std::io::{BufReader, BufWriter};
 
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use walkdir::WalkDir;
 
 use std::fs;
 
-use db::distribution::{MyDistribution, MyDistributionTrait};
-use db::history::{generate_mult_histories, HistoryParams};
-use db::history::History;
-
-use zipf::ZipfDistribution;
+use dbcop::db::distribution::KeyDistribution;
+use dbcop::db::error::DbcopError;
+use dbcop::db::format::{read_history, write_history, Encoding};
+use dbcop::db::history::{generate_mult_histories, HistoryParams};
+use dbcop::db::history::{AbortKind, History};
+use dbcop::db::placement::{Placement, PlacementPolicy};
+use dbcop::db::retry::{Backoff, RetryPolicy};
+use dbcop::db::workload::Workload;
 
 use env_logger::{Builder, Target};
-use log::{info};
-
-struct HotspotDistribution {
-    hot_probability: Bernoulli,
-    hot_key: Uniform<usize>,
-    non_hot_key: Uniform<usize>,
-}
-
-impl Distribution<usize> for HotspotDistribution {
-    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> usize {
-        if self.hot_probability.sample(rng) {
-            self.hot_key.sample(rng)
-        } else {
-            self.non_hot_key.sample(rng)
-        }
-    }
-}
-
-impl HotspotDistribution {
-    fn new(n_variables: usize) -> HotspotDistribution {
-        let hot_key_max = n_variables / 5;
-        HotspotDistribution {
-            hot_probability: Bernoulli::new(0.8).unwrap(),
-            hot_key: Uniform::new(0, hot_key_max),
-            non_hot_key: Uniform::new(hot_key_max, n_variables),
-        }
-    }
-}
+use log::{error, info, warn};
 
 #[derive(Parser)]
 #[clap(name = "dbcop", author = "Ranadeep", about = "Generates histories or verifies executed histories")]
@@ -67,25 +53,43 @@ enum Commands {
         #[clap(long = "nhist", default_value_t = 1, help = "Number of histories to generate")]
         n_history: usize,
 
-        #[clap(long = "nnode", short = 'n', help = "Number of nodes per history")]
+        #[clap(long = "nnode", short = 'n', value_parser = at_least_one, help = "Number of nodes per history")]
         n_node: usize,
 
-        #[clap(long = "nvar", short = 'v', help = "Number of variables per history")]
+        #[clap(long = "nsession", value_parser = at_least_one, help = "Number of sessions per history, the number of nodes if not given")]
+        n_session: Option<usize>,
+
+        #[clap(long = "nvar", short = 'v', value_parser = at_least_one, help = "Number of variables per history")]
         n_variable: usize,
 
         #[clap(long = "ntxn", short = 't', help = "Number of transactions per history")]
         n_transaction: usize,
 
-        #[clap(long = "nevt", short = 'e', help = "Number of events per transactions")]
+        #[clap(long = "nevt", short = 'e', value_parser = at_least_one, help = "Number of events per transactions")]
         n_event: usize,
 
-        #[clap(long = "readp", default_value_t = 0.5, help = "Probability for an event to be a read")]
+        #[clap(long = "readp", default_value_t = 0.5, value_parser = probability, help = "Probability for an event of the random workload to be a read")]
         read_probability: f64,
 
-        #[clap(value_enum, long = "key_distrib", default_value_t = KeyDistribution::Uniform, help = "Key access distribution")]
-        key_distribution: KeyDistribution,
+        #[clap(value_enum, long, default_value_t = Workload::Random, help = "Shape of the transactions")]
+        workload: Workload,
+
+        #[clap(value_enum, long = "key_distrib", help = "Key access distribution, the one of the workload if not given")]
+        key_distribution: Option<KeyDistributionKind>,
+
+        #[clap(long = "zipf-exponent", default_value_t = 0.5, value_parser = positive, help = "Exponent of the zipf and latest distributions")]
+        zipf_exponent: f64,
+
+        #[clap(long = "hot-probability", default_value_t = 0.8, value_parser = probability, help = "Probability of accessing the hot variables of the hotspot distribution")]
+        hot_probability: f64,
 
-        #[clap(long, default_value_t = 0.0, help = "Proportion of long transactions")]
+        #[clap(long = "hot-fraction", default_value_t = 0.2, value_parser = fraction, help = "Fraction of the variables that are hot in the hotspot distribution")]
+        hot_fraction: f64,
+
+        #[clap(long = "exponential-rate", default_value_t = 10.0, value_parser = positive, help = "Rate of the exponential distribution, relative to the number of variables")]
+        exponential_rate: f64,
+
+        #[clap(long, default_value_t = 0.0, value_parser = probability, help = "Proportion of long transactions")]
         longtxn_proportion: f64,
 
         #[clap(long, default_value_t = 10.0, help = "Times of size of long transactions compared to regular txns")]
@@ -93,6 +97,9 @@ enum Commands {
 
         #[clap(long, action, help = "Randomize size of transactions")]
         random_txn_size: bool,
+
+        #[clap(long, help = "Seed of the first history, random if not given; history i is generated with seed + i")]
+        seed: Option<u64>,
     },
     #[clap(about = "Print executed history")]
     Print {
@@ -120,17 +127,83 @@ enum Commands {
 
         #[clap(long = "db", value_enum)]
         database: Database,
-    }
+
+        #[clap(long = "max-attempts", default_value_t = 10, help = "Attempts at a transaction before giving up on it, 0 for no limit")]
+        max_attempts: usize,
+
+        #[clap(value_enum, long, default_value_t = Backoff::Exponential, help = "Delay between attempts at a transaction")]
+        backoff: Backoff,
+
+        #[clap(long = "backoff-ms", default_value_t = 10, help = "Delay before the first retry in milliseconds")]
+        backoff_ms: u64,
+
+        #[clap(long = "max-backoff-ms", default_value_t = 1000, help = "Bound on the delay before a retry in milliseconds")]
+        max_backoff_ms: u64,
+
+        #[clap(value_enum, long, default_value_t = PlacementPolicy::RoundRobin, help = "Assignment of sessions to nodes")]
+        placement: PlacementPolicy,
+
+        #[clap(long = "pin", value_delimiter = ',', help = "Node indices of the pinned and single placements")]
+        pin: Vec<usize>,
+
+        #[clap(value_enum, long = "sim-isolation", default_value_t = SimulatedIsolation::Serializable, help = "Isolation level of the simulated database")]
+        sim_isolation: SimulatedIsolation,
+
+        #[clap(long = "sim-nodes", default_value_t = 3, help = "Number of nodes of the simulated database")]
+        sim_nodes: usize,
+
+        #[clap(value_enum, long = "tidb-txn-mode", default_value_t = TiDBTxnMode::Optimistic, help = "Transaction mode of TiDB")]
+        tidb_txn_mode: TiDBTxnMode,
+
+        #[clap(value_enum, long = "sqlite-begin", default_value_t = SqliteBegin::Deferred, help = "When SQLite transactions take the write lock")]
+        sqlite_begin: SqliteBegin,
+    },
+    #[clap(about = "Verify executed histories against a consistency level")]
+    Verify {
+        #[clap(short = 'd', help = "Directory containing executed histories")]
+        directory: PathBuf,
+
+        #[clap(value_enum, long = "cons", help = "Consistency level to check")]
+        consistency: Consistency,
+
+        #[clap(long, action, help = "Check serializability with the SAT solver instead of the commit order search")]
+        sat: bool,
+
+        #[clap(long, action, help = "Write a Graphviz rendering of each violation next to its history")]
+        dot: bool,
+
+        #[clap(long, action, help = "Shrink each violating history and write it next to the original")]
+        shrink: bool,
+    },
+    #[clap(about = "Check executed histories for session guarantees")]
+    Sessions {
+        #[clap(short = 'd', help = "Directory containing executed histories")]
+        directory: PathBuf,
+
+        #[clap(value_enum, long = "guarantee", help = "Session guarantees to check, all by default")]
+        guarantees: Vec<SessionGuarantee>,
+    },
+    #[clap(about = "Classify executed histories into Adya's phenomena")]
+    Anomalies {
+        #[clap(short = 'd', help = "Directory containing executed histories")]
+        directory: PathBuf,
+    },
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-enum KeyDistribution {
-    Uniform, Zipf, Hotspot
+enum KeyDistributionKind {
+    Uniform, Zipf, Hotspot, Latest, Exponential, Sequential, Partitioned
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Database {
-    Postgres, PostgresSer, Dgraph, Galera, Mysql, Tdsql
+    Postgres, PostgresSer, Dgraph, Galera, Mysql, Tdsql, Tidb, Yugabyte, YugabyteSer, Simulated, Sqlite,
+    #[cfg(feature = "cockroachdb")]
+    Cockroachdb,
+    #[cfg(feature = "antidotedb")]
+    Antidotedb,
+    #[cfg(feature = "memgraph")]
+    Memgraph,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -138,6 +211,88 @@ enum FileFormat {
     Bincode, Json
 }
 
+/// Parses a count, at least 1.
+fn at_least_one(s: &str) -> Result<usize, String> {
+    let n: usize = s.parse().map_err(|e| format!("{}", e))?;
+    if n >= 1 {
+        Ok(n)
+    } else {
+        Err(format!("{} is not at least 1", n))
+    }
+}
+
+/// Parses a probability, between 0 and 1.
+fn probability(s: &str) -> Result<f64, String> {
+    let p: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if (0.0..=1.0).contains(&p) {
+        Ok(p)
+    } else {
+        Err(format!("{} is not between 0 and 1", p))
+    }
+}
+
+/// Parses a fraction of the variables, strictly between 0 and 1 so that both parts are non-empty.
+fn fraction(s: &str) -> Result<f64, String> {
+    let p: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if p > 0.0 && p < 1.0 {
+        Ok(p)
+    } else {
+        Err(format!("{} is not strictly between 0 and 1", p))
+    }
+}
+
+/// Parses a positive finite number.
+fn positive(s: &str) -> Result<f64, String> {
+    let x: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if x > 0.0 && x.is_finite() {
+        Ok(x)
+    } else {
+        Err(format!("{} is not a positive number", x))
+    }
+}
+
+/// Reads the history of `path`, logging why if it cannot be read.
+fn load_history(path: &Path) -> Option<History> {
+    let history = File::open(path)
+        .map_err(|e| DbcopError::Serialization(e.to_string()))
+        .and_then(|file| read_history(BufReader::new(file)));
+    match history {
+        Ok(history) => Some(history),
+        Err(e) => {
+            error!("Reading history {:?} failed: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Writes `hist` to `path`, logging why if it cannot be written.
+fn store_history(path: &Path, hist: &History, encoding: Encoding) -> bool {
+    let written = File::create(path)
+        .map_err(|e| DbcopError::Serialization(e.to_string()))
+        .and_then(|file| write_history(BufWriter::new(file), hist, encoding));
+    if let Err(e) = &written {
+        error!("Writing history {:?} failed: {}", path, e);
+    }
+    written.is_ok()
+}
+
+/// The executed histories under `directory` in order, the entries that cannot be read skipped.
+fn history_paths(directory: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = WalkDir::new(directory)
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry.into_path()),
+            Err(e) => {
+                error!("Reading an entry of {:?} failed: {}", directory, e);
+                None
+            }
+        })
+        .filter(|path| path.is_file() && path.file_name().is_some_and(|name| name == "history.bincode"))
+        .collect();
+    paths.sort();
+    paths
+}
+
 fn main() {
     Builder::new()
         .filter_level(log::LevelFilter::Info)
@@ -148,86 +303,105 @@ fn main() {
 
     match cli.command {
         Commands::Print { directory } => {
-            let v_path = directory.join("history.bincode");
-            let file = File::open(v_path).unwrap();
-            let buf_reader = BufReader::new(file);
-            let hist: History = bincode::deserialize_from(buf_reader).unwrap();
+            let hist = match load_history(&directory.join("history.bincode")) {
+                Some(hist) => hist,
+                None => std::process::exit(1),
+            };
 
-            println!("{:?}", hist);
+            println!("{} {:?}", hist.get_info(), hist.get_params());
+            for (i_session, session) in hist.get_data().iter().enumerate() {
+                match hist.get_session_nodes().get(i_session) {
+                    Some(node) => println!("session {} (node {}):", i_session, node),
+                    None => println!("session {}:", i_session),
+                }
+                for (i_txn, transaction) in session.iter().enumerate() {
+                    let txn = TransactionId { session: i_session, index: i_txn };
+                    let mut line = format!("  {} {:?}", txn, transaction);
+                    if let Some(abort) = &transaction.abort {
+                        line += &format!(" {}", abort);
+                    }
+                    if transaction.gave_up {
+                        line += " (gave up)";
+                    }
+                    println!("{}", line);
+                }
+            }
         }
         Commands::Convert { directory, format  } => {
-            WalkDir::new(&directory).into_iter().for_each(
-                |entry| {
-                    let entry = entry.unwrap();
-                    let path = entry.path();
-                    if path.is_file() {
-                        let file_name = path.file_name().unwrap().to_str().unwrap();
-                        match format {
-                            FileFormat::Bincode => {
-                                if file_name.starts_with("hist") && file_name.ends_with(".bincode") {
-                                    // convert bincode to json
-                                    let input_path = &directory.join(file_name);
-                                    let file = File::open(input_path).unwrap();
-                                    let buf_reader = BufReader::new(file);
-                                    let hist: History = bincode::deserialize_from(buf_reader).unwrap();
-                                    let output_path: &PathBuf = &directory.join(file_name.replace("bincode", "json"));
-                                    fs::write(
-                                        output_path,
-                                        serde_json::to_string_pretty(&hist).unwrap()
-                                    ).unwrap();
-                                    info!("Converting {:?} to {:?}", file_name, output_path.as_path().file_name().unwrap());
-                                }
-                            },
-                            FileFormat::Json => {
-                                if file_name.starts_with("hist") && file_name.ends_with(".json") {
-                                    // convert json to bincode
-                                    let input_path = &directory.join(file_name);
-                                    let file = File::open(input_path).unwrap();
-                                    let buf_reader = BufReader::new(file);
-                                    let hist: History = serde_json::from_reader(buf_reader).unwrap();
-                                    let output_path: &PathBuf = &directory.join(file_name.replace(".json", "-back.bincode"));
-                                    fs::write(
-                                        output_path, 
-                                        bincode::serialize(&hist).unwrap())
-                                    .unwrap();
-                                    info!("Converting {:?} to {:?}", file_name, output_path.as_path().file_name().unwrap());
-                                }
-                            },
-                        }
-                    } 
+            for entry in WalkDir::new(&directory) {
+                let path = match entry {
+                    Ok(entry) => entry.into_path(),
+                    Err(e) => {
+                        error!("Reading an entry of {:?} failed: {}", directory, e);
+                        continue;
+                    }
+                };
+                let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                    Some(file_name) if path.is_file() && file_name.starts_with("hist") => file_name,
+                    _ => continue,
+                };
+                let (output_path, encoding) = match format {
+                    // convert bincode to json
+                    FileFormat::Bincode if file_name.ends_with(".bincode") => {
+                        (directory.join(file_name.replace("bincode", "json")), Encoding::Json)
+                    }
+                    // convert json to bincode
+                    FileFormat::Json if file_name.ends_with(".json") => {
+                        (directory.join(file_name.replace(".json", "-back.bincode")), Encoding::Bincode)
+                    }
+                    _ => continue,
+                };
+                if let Some(hist) = load_history(&directory.join(file_name)) {
+                    if store_history(&output_path, &hist, encoding) {
+                        info!("Converting {:?} to {:?}", file_name, output_path.file_name().unwrap_or_default());
+                    }
                 }
-            );
+            }
         }
-        Commands::Generate { g_directory, n_history, n_node, n_variable, n_transaction, n_event, read_probability, key_distribution, longtxn_proportion, longtxn_size, random_txn_size } => {
+        Commands::Generate { g_directory, n_history, n_node, n_session, n_variable, n_transaction, n_event, read_probability, workload, key_distribution, zipf_exponent, hot_probability, hot_fraction, exponential_rate, longtxn_proportion, longtxn_size, random_txn_size, seed } => {
             if !g_directory.is_dir() {
                 fs::create_dir_all(&g_directory).expect("failed to create directory");
             }
 
-            let distribution: Box<dyn MyDistributionTrait> =
-                match key_distribution {
-                    KeyDistribution::Uniform => Box::new(MyDistribution::new(Uniform::new(0, n_variable))),
-                    KeyDistribution::Zipf => Box::new(MyDistribution::new(
-                        ZipfDistribution::new(n_variable, 0.5)
-                            .unwrap()
-                            .map(|x| x - 1),
-                    )),
-                    KeyDistribution::Hotspot => {
-                        Box::new(MyDistribution::new(HotspotDistribution::new(n_variable)))
-                    }
-                };
+            if workload == Workload::Tpcc && key_distribution.is_some() {
+                error!("--key_distrib cannot be used with --workload tpcc, whose transactions pick their variables uniformly");
+                std::process::exit(1);
+            }
+
+            if key_distribution == Some(KeyDistributionKind::Hotspot) && n_variable < 2 {
+                error!("the hotspot distribution needs at least 2 variables, one hot and one other");
+                std::process::exit(1);
+            }
+
+            let distribution = match key_distribution {
+                None => workload.key_distribution(),
+                Some(KeyDistributionKind::Uniform) => KeyDistribution::Uniform,
+                Some(KeyDistributionKind::Zipf) => KeyDistribution::Zipf { exponent: zipf_exponent },
+                Some(KeyDistributionKind::Hotspot) => KeyDistribution::Hotspot { hot_probability, hot_fraction },
+                Some(KeyDistributionKind::Latest) => KeyDistribution::Latest { exponent: zipf_exponent },
+                Some(KeyDistributionKind::Exponential) => KeyDistribution::Exponential { rate: exponential_rate },
+                Some(KeyDistributionKind::Sequential) => KeyDistribution::Sequential,
+                Some(KeyDistributionKind::Partitioned) => KeyDistribution::Partitioned,
+            };
+
+            let seed = seed.unwrap_or_else(rand::random);
+            info!("Generating histories with seed {}", seed);
 
             let mut histories = generate_mult_histories(
                 HistoryParams {
                     n_hist: n_history,
                     n_node,
+                    n_session: n_session.unwrap_or(n_node),
                     n_variable,
                     n_transaction,
                     n_event,
                     read_probability,
-                    key_distribution: distribution.as_ref(),
+                    key_distribution: distribution,
+                    workload,
                     longtxn_proportion,
                     longtxn_size,
                     random_txn_size,
+                    seed,
                 }
             );
 
@@ -235,24 +409,212 @@ fn main() {
                 let file = File::create(g_directory.join(format!("hist-{:05}.bincode", hist.get_id())))
                     .expect("couldn't create bincode file");
                 let buf_writer = BufWriter::new(file);
-                bincode::serialize_into(buf_writer, &hist)
+                write_history(buf_writer, &hist, Encoding::Bincode)
                     .expect("dumping history to bincode file went wrong");
             }
         }
-        Commands::Run { hist_dir, hist_out, addrs, database } => {
+        Commands::Run { hist_dir, hist_out, addrs, database, max_attempts, backoff, backoff_ms, max_backoff_ms, placement, pin, tidb_txn_mode, sim_isolation, sim_nodes, sqlite_begin } => {
             fs::create_dir_all(&hist_out).expect("couldn't create directory");
             let addrs_str = addrs.iter().map(|addr| addr.as_str()).collect();
 
-            let mut cluster: Box<dyn Cluster<DynNode>> = match database {
-                Database::Postgres => Box::new(DynCluster::new(PostgresCluster::new(&addrs_str))),
-                Database::PostgresSer => Box::new(DynCluster::new(PostgresSERCluster::new(&addrs_str))),
-                Database::Dgraph => Box::new(DynCluster::new(DGraphCluster::new(&addrs_str))),
-                Database::Galera => Box::new(DynCluster::new(GaleraCluster::new(&addrs_str))),
-                Database::Mysql => Box::new(DynCluster::new(MySQLCluster::new(&addrs_str))),
-                Database::Tdsql => Box::new(DynCluster::new(TDSQLCluster::new(&addrs_str))),
+            let retry = RetryPolicy {
+                max_attempts: RetryPolicy::attempt_limit(max_attempts),
+                backoff,
+                delay: Duration::from_millis(backoff_ms),
+                max_delay: Duration::from_millis(max_backoff_ms),
+            };
+            let placement = Placement { policy: placement, nodes: pin };
+            // the SQLite database is the file given as address
+            let sqlite_path = addrs.first().map_or_else(|| hist_out.join("dbcop.sqlite"), PathBuf::from);
+
+            let run = || -> Result<usize, DbcopError> {
+                let mut cluster: Box<dyn Cluster<DynNode>> = match database {
+                    Database::Postgres => Box::new(DynCluster::new(PostgresCluster::new(&addrs_str)?)),
+                    Database::PostgresSer => Box::new(DynCluster::new(PostgresSERCluster::new(&addrs_str)?)),
+                    Database::Dgraph => Box::new(DynCluster::new(DGraphCluster::new(&addrs_str)?)),
+                    Database::Galera => Box::new(DynCluster::new(GaleraCluster::new(&addrs_str)?)),
+                    Database::Mysql => Box::new(DynCluster::new(MySQLCluster::new(&addrs_str)?)),
+                    Database::Tdsql => Box::new(DynCluster::new(TDSQLCluster::new(&addrs_str)?)),
+                    Database::Tidb => Box::new(DynCluster::new(TiDBCluster::new(&addrs_str, tidb_txn_mode)?)),
+                    Database::Yugabyte => Box::new(DynCluster::new(YugabyteCluster::new(&addrs_str)?)),
+                    Database::YugabyteSer => Box::new(DynCluster::new(YugabyteSERCluster::new(&addrs_str)?)),
+                    Database::Simulated => Box::new(DynCluster::new(SimulatedCluster::new(sim_nodes, sim_isolation)?)),
+                    Database::Sqlite => Box::new(DynCluster::new(SqliteCluster::new(&sqlite_path, sqlite_begin)?)),
+                    #[cfg(feature = "cockroachdb")]
+                    Database::Cockroachdb => Box::new(DynCluster::new(CockroachCluster::new(&addrs_str)?)),
+                    #[cfg(feature = "antidotedb")]
+                    Database::Antidotedb => Box::new(DynCluster::new(AntidoteCluster::new(&addrs_str)?)),
+                    #[cfg(feature = "memgraph")]
+                    Database::Memgraph => Box::new(DynCluster::new(MemgraphCluster::new(&addrs_str)?)),
+                };
+                cluster.execute_all(&hist_dir, &hist_out, 100, &retry, &placement)
             };
 
-            cluster.execute_all(&hist_dir.as_path(), &hist_out.as_path(), 100);
+            if let Err(e) = run() {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Verify { directory, consistency, sat, dot, shrink } => {
+            let mut verifier = Verifier::new(consistency);
+            verifier.sat(sat);
+            let mut n_violation = 0;
+
+            let paths = history_paths(&directory);
+
+            for path in paths.iter() {
+                let hist = match load_history(path) {
+                    Some(hist) => hist,
+                    None => continue,
+                };
+
+                if consistency == Consistency::StrictSerializable
+                    && hist
+                        .get_data()
+                        .iter()
+                        .flatten()
+                        .any(|transaction| transaction.success && transaction.attempt.is_none())
+                {
+                    warn!(
+                        "{:?} has committed transactions without timing, strict serializability does not order them in real time",
+                        path
+                    );
+                }
+
+                match verifier.verify(hist.get_data()) {
+                    Ok(()) => info!("{:?} satisfies {:?}", path, consistency),
+                    Err(violation) => {
+                        warn!("{:?} violates {:?}: {}", path, consistency, violation);
+                        if let Some(witness) = Witness::new(&violation, hist.get_data()) {
+                            println!("{}", witness);
+                            if dot {
+                                let dot_path = path.with_file_name("violation.dot");
+                                fs::write(&dot_path, witness.to_dot(hist.get_data())).unwrap();
+                                info!("Graph of the violation written to {:?}", dot_path);
+                            }
+                        }
+                        if shrink {
+                            let (data, kept) = verifier.shrink(hist.get_data()).unwrap();
+                            info!(
+                                "Shrunk to {} transactions in {} sessions",
+                                data.iter().map(|session| session.len()).sum::<usize>(),
+                                data.len()
+                            );
+                            let mut shrunk = History::new(
+                                hist.get_cloned_params(),
+                                hist.get_info().to_string(),
+                                hist.get_start(),
+                                hist.get_end(),
+                                data,
+                            );
+                            // histories written before placement was recorded have no nodes to keep
+                            shrunk.set_session_nodes(
+                                kept.iter().filter_map(|&i| hist.get_session_nodes().get(i).copied()).collect(),
+                            );
+                            let bincode_path = path.with_file_name("history-shrunk.bincode");
+                            let json_path = path.with_file_name("history-shrunk.json");
+                            if store_history(&bincode_path, &shrunk, Encoding::Bincode)
+                                && store_history(&json_path, &shrunk, Encoding::Json)
+                            {
+                                info!("Shrunk history written to {:?} and {:?}", bincode_path, json_path);
+                            }
+                        }
+                        n_violation += 1;
+                    }
+                }
+            }
+
+            info!("{} of {} histories violate {:?}", n_violation, paths.len(), consistency);
+
+            if n_violation > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Sessions { directory, mut guarantees } => {
+            if guarantees.is_empty() {
+                guarantees = SessionGuarantee::value_variants().to_vec();
+            }
+            let mut n_violation = 0;
+
+            let paths = history_paths(&directory);
+
+            for path in paths.iter() {
+                let hist = match load_history(path) {
+                    Some(hist) => hist,
+                    None => continue,
+                };
+
+                let history = match TransactionalHistory::new(hist.get_data()) {
+                    Ok(history) => history,
+                    Err(violation) => {
+                        warn!("{:?} is malformed: {}", path, violation);
+                        n_violation += 1;
+                        continue;
+                    }
+                };
+                let mut violated = false;
+                for &guarantee in guarantees.iter() {
+                    let violations = check_session_guarantee(&history, guarantee);
+                    if violations.is_empty() {
+                        info!("{:?} satisfies {:?}", path, guarantee);
+                    }
+                    for violation in violations.iter() {
+                        warn!("{:?} violates {:?}: {}", path, guarantee, violation);
+                        violated = true;
+                    }
+                }
+                if violated {
+                    n_violation += 1;
+                }
+            }
+
+            info!("{} of {} histories violate a session guarantee", n_violation, paths.len());
+
+            if n_violation > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Anomalies { directory } => {
+            let paths = history_paths(&directory);
+
+            // aborted attempts by database and isolation level, then by reason
+            let mut aborts: BTreeMap<String, BTreeMap<AbortKind, usize>> = BTreeMap::new();
+
+            for path in paths.iter() {
+                let hist = match load_history(path) {
+                    Some(hist) => hist,
+                    None => continue,
+                };
+
+                let report = adya::classify(hist.get_data());
+                let database_aborts = aborts.entry(hist.get_info().to_string()).or_default();
+                for (&kind, &count) in report.aborts.iter() {
+                    *database_aborts.entry(kind).or_default() += count;
+                }
+                info!(
+                    "{:?}: G0 {}, G1a {}, G1b {}, G1c {}, G-single {}, G2 {} ({} blind writes left out of G0)",
+                    path,
+                    report.g0.count,
+                    report.g1a.count,
+                    report.g1b.count,
+                    report.g1c.count,
+                    report.g_single.count,
+                    report.g2.count,
+                    report.blind_writes
+                );
+                let report_path = path.with_file_name("anomalies.json");
+                fs::write(&report_path, serde_json::to_string_pretty(&report).unwrap()).unwrap();
+            }
+
+            for (database, database_aborts) in aborts.iter() {
+                let breakdown: Vec<String> = database_aborts
+                    .iter()
+                    .map(|(kind, count)| format!("{} {}", kind, count))
+                    .collect();
+                info!("{} aborts: {}", database, breakdown.join(", "));
+            }
+            let aborts_path = directory.join("aborts.json");
+            fs::write(&aborts_path, serde_json::to_string_pretty(&aborts).unwrap()).unwrap();
         }
     }
 }
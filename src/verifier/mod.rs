@@ -0,0 +1,48 @@
+mod shrink;
+
+use crate::consistency::causal::check_causal;
+use crate::consistency::ra::check_read_atomic;
+use crate::consistency::rc::check_read_committed;
+use crate::consistency::sat;
+use crate::consistency::ser::{check_serializable, check_strict_serializable};
+use crate::consistency::util::TransactionalHistory;
+use crate::consistency::{Consistency, Violation};
+use crate::db::history::Session;
+
+#[derive(Debug)]
+pub struct Verifier {
+    consistency_model: Consistency,
+    use_sat: bool,
+}
+
+impl Verifier {
+    pub fn new(consistency_model: Consistency) -> Self {
+        Verifier {
+            consistency_model,
+            use_sat: false,
+        }
+    }
+
+    /// Checks serializability with the SAT solver instead of the commit order search.
+    pub fn sat(&mut self, use_sat: bool) {
+        self.use_sat = use_sat;
+    }
+
+    pub fn verify(&self, histories: &[Session]) -> Result<(), Violation> {
+        let history = TransactionalHistory::new(histories)?;
+
+        match self.consistency_model {
+            Consistency::ReadCommitted => check_read_committed(&history),
+            Consistency::ReadAtomic => check_read_atomic(&history),
+            Consistency::Causal => check_causal(&history),
+            Consistency::SnapshotIsolation => sat::check_snapshot_isolation(&history),
+            Consistency::Serializable if self.use_sat => sat::check_serializable(&history),
+            // the commit order search cannot explain a failure, the SAT encoding can
+            Consistency::Serializable => check_serializable(&history)
+                .or_else(|violation| sat::check_serializable(&history).and(Err(violation))),
+            Consistency::StrictSerializable if self.use_sat => sat::check_strict_serializable(&history),
+            Consistency::StrictSerializable => check_strict_serializable(&history)
+                .or_else(|violation| sat::check_strict_serializable(&history).and(Err(violation))),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeSet;
+use std::mem::discriminant;
+
+use crate::consistency::util::Variable;
+use crate::consistency::Violation;
+use crate::db::history::Session;
+
+use super::Verifier;
+
+/// Reduces `items` to a subset still passing `test`, which `items` itself must pass.
+///
+/// This is the ddmin algorithm restricted to complements: the items are split into `n`
+/// chunks and a chunk is dropped whenever the rest still passes, otherwise the split is
+/// refined until single items are tried.
+fn ddmin<T: Clone>(mut items: Vec<T>, test: impl Fn(&[T]) -> bool) -> Vec<T> {
+    let mut n = 2;
+    while items.len() >= 2 {
+        let chunk_len = items.len().div_ceil(n);
+        let mut reduced = false;
+        for start in (0..items.len()).step_by(chunk_len) {
+            let complement: Vec<T> = items[..start]
+                .iter()
+                .chain(items[(start + chunk_len).min(items.len())..].iter())
+                .cloned()
+                .collect();
+            if test(&complement) {
+                items = complement;
+                n = (n - 1).max(2);
+                reduced = true;
+                break;
+            }
+        }
+        if !reduced {
+            if n >= items.len() {
+                break;
+            }
+            n = (2 * n).min(items.len());
+        }
+    }
+    if items.len() == 1 && test(&[]) {
+        items.clear();
+    }
+    items
+}
+
+fn without_variables(histories: &[Session], variables: &BTreeSet<Variable>) -> Vec<Session> {
+    histories
+        .iter()
+        .map(|session| {
+            session
+                .iter()
+                .map(|transaction| {
+                    let mut transaction = transaction.clone();
+                    transaction.events.retain(|event| variables.contains(&event.variable));
+                    transaction
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn with_transactions(histories: &[Session], transactions: &[(usize, usize)]) -> Vec<Session> {
+    let mut sessions: Vec<Session> = vec![Vec::new(); histories.len()];
+    for &(i_session, i_txn) in transactions.iter() {
+        sessions[i_session].push(histories[i_session][i_txn].clone());
+    }
+    sessions.retain(|session| !session.is_empty());
+    sessions
+}
+
+impl Verifier {
+    /// Shrinks a violating history to a smaller one violating the consistency level the same way.
+    ///
+    /// Sessions, then all events of a variable, then transactions are removed as long as the
+    /// violation persists, until none can be removed. A violation explained by dependency
+    /// cycles is only kept by well-formed sub-histories, so transactions whose writes are
+    /// still read are never removed. Returns the shrunk history with the index in `histories`
+    /// of each of its sessions, or `None` if the history is not violating.
+    pub fn shrink(&self, histories: &[Session]) -> Option<(Vec<Session>, Vec<usize>)> {
+        let violation = self.verify(histories).err()?;
+        let explained_by_cycles =
+            |v: &Violation| matches!(v, Violation::Cycle { .. } | Violation::NoCommitOrder { .. });
+        let same_violation = |candidate: &[Session]| match self.verify(candidate) {
+            Ok(()) => false,
+            Err(v) if explained_by_cycles(&violation) => explained_by_cycles(&v),
+            Err(v) => discriminant(&v) == discriminant(&violation),
+        };
+
+        let mut current = histories.to_vec();
+        let mut kept: Vec<usize> = (0..histories.len()).collect();
+        let size = |histories: &[Session]| -> usize {
+            histories.iter().flatten().map(|transaction| 1 + transaction.events.len()).sum()
+        };
+
+        loop {
+            let prev_size = size(&current);
+
+            let sessions = ddmin((0..current.len()).collect(), |sessions: &[usize]| {
+                same_violation(&sessions.iter().map(|&i| current[i].clone()).collect::<Vec<_>>())
+            });
+            current = sessions.iter().map(|&i| current[i].clone()).collect();
+            kept = sessions.iter().map(|&i| kept[i]).collect();
+
+            let variables: Vec<Variable> = current
+                .iter()
+                .flatten()
+                .flat_map(|transaction| transaction.events.iter().map(|event| event.variable))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            let variables = ddmin(variables, |variables| {
+                same_violation(&without_variables(&current, &variables.iter().copied().collect()))
+            });
+            current = without_variables(&current, &variables.into_iter().collect());
+
+            let transactions: Vec<(usize, usize)> = current
+                .iter()
+                .enumerate()
+                .flat_map(|(i_session, session)| (0..session.len()).map(move |i_txn| (i_session, i_txn)))
+                .collect();
+            let transactions = ddmin(transactions, |transactions| {
+                same_violation(&with_transactions(&current, transactions))
+            });
+            current = with_transactions(&current, &transactions);
+            // sessions left without transactions are dropped by `with_transactions`
+            kept = kept
+                .iter()
+                .enumerate()
+                .filter(|&(i_session, _)| transactions.iter().any(|&(i, _)| i == i_session))
+                .map(|(_, &i)| i)
+                .collect();
+
+            if size(&current) == prev_size {
+                return Some((current, kept));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::testing::{committed, read};
+    use crate::consistency::Consistency;
+    use crate::db::history::Event;
+
+    #[test]
+    fn shrink_keeps_the_indices_of_violating_sessions() {
+        // sessions 1 and 3 form a write skew, sessions 0 and 2 are unrelated
+        let histories = vec![
+            vec![committed(vec![Event::write(2, 1)])],
+            vec![committed(vec![read(0, 0), Event::write(1, 1)])],
+            vec![committed(vec![read(2, 1)]), committed(vec![Event::write(3, 1)])],
+            vec![committed(vec![read(1, 0), Event::write(0, 1)])],
+        ];
+        let (shrunk, kept) = Verifier::new(Consistency::Serializable).shrink(&histories).unwrap();
+        assert_eq!(kept, [1, 3]);
+        assert_eq!(shrunk, [histories[1].clone(), histories[3].clone()]);
+    }
+}
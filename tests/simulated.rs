@@ -0,0 +1,181 @@
+//! Generates histories, runs them on the simulated database at each isolation level and checks
+//! the verdicts of the checkers on the executed histories.
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use dbcop::clients::{SimulatedCluster, SimulatedIsolation};
+use dbcop::consistency::Consistency;
+use dbcop::db::cluster::Cluster;
+use dbcop::db::distribution::KeyDistribution;
+use dbcop::db::format::{read_history, write_history, Encoding};
+use dbcop::db::history::{generate_mult_histories, History, HistoryParams};
+use dbcop::db::placement::Placement;
+use dbcop::db::retry::RetryPolicy;
+use dbcop::db::workload::Workload;
+use dbcop::verifier::Verifier;
+
+const N_HIST: usize = 6;
+
+static N_RUN: AtomicUsize = AtomicUsize::new(0);
+
+/// Directory removed when the test ends.
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Generates histories of six sessions over four variables, runs them on three simulated nodes
+/// at `isolation` and reads back the executed histories.
+fn generate_and_run(isolation: SimulatedIsolation) -> Vec<History> {
+    let run = N_RUN.fetch_add(1, Ordering::SeqCst);
+    let dir = TempDir(std::env::temp_dir().join(format!("dbcop-simulated-{}-{}", std::process::id(), run)));
+    let gen_dir = dir.0.join("gen");
+    let out_dir = dir.0.join("out");
+    fs::create_dir_all(&gen_dir).unwrap();
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let histories = generate_mult_histories(HistoryParams {
+        n_hist: N_HIST,
+        n_node: 3,
+        n_session: 6,
+        n_variable: 4,
+        n_transaction: 6,
+        n_event: 4,
+        read_probability: 0.5,
+        longtxn_proportion: 0.0,
+        longtxn_size: 10.0,
+        key_distribution: KeyDistribution::Uniform,
+        workload: Workload::Random,
+        random_txn_size: false,
+        seed: 11,
+    });
+    for history in histories.iter() {
+        let file = File::create(gen_dir.join(format!("hist-{:05}.bincode", history.get_id()))).unwrap();
+        write_history(file, history, Encoding::Bincode).unwrap();
+    }
+
+    let retry = RetryPolicy {
+        max_attempts: None,
+        delay: Duration::ZERO,
+        ..Default::default()
+    };
+    let mut cluster = SimulatedCluster::new(3, isolation).unwrap();
+    let executed = cluster.execute_all(&gen_dir, &out_dir, 0, &retry, &Placement::default()).unwrap();
+    assert_eq!(executed, N_HIST);
+
+    (0..N_HIST)
+        .map(|id| {
+            let path = out_dir.join(format!("hist-{:05}", id)).join("history.bincode");
+            let history = read_history(File::open(path).unwrap()).unwrap();
+            assert_eq!(history.get_session_nodes(), &vec![0, 1, 2, 0, 1, 2]);
+            history
+        })
+        .collect()
+}
+
+fn satisfies(history: &History, level: Consistency) -> bool {
+    Verifier::new(level).verify(history.get_data()).is_ok()
+}
+
+/// Every history satisfies `allowed`, and some history violates `violated` if given.
+fn check(isolation: SimulatedIsolation, allowed: &[Consistency], violated: Option<Consistency>) {
+    let histories = generate_and_run(isolation);
+    for history in histories.iter() {
+        for &level in allowed.iter() {
+            assert!(satisfies(history, level), "{:?} violates {:?}", isolation, level);
+        }
+    }
+    if let Some(level) = violated {
+        assert!(
+            histories.iter().any(|history| !satisfies(history, level)),
+            "no history of {:?} violates {:?}",
+            isolation,
+            level
+        );
+    }
+}
+
+#[test]
+fn read_committed() {
+    check(SimulatedIsolation::ReadCommitted, &[Consistency::ReadCommitted], None);
+}
+
+#[test]
+fn read_atomic() {
+    check(
+        SimulatedIsolation::ReadAtomic,
+        &[Consistency::ReadCommitted, Consistency::ReadAtomic],
+        None,
+    );
+}
+
+#[test]
+fn causal() {
+    check(
+        SimulatedIsolation::Causal,
+        &[Consistency::ReadAtomic, Consistency::Causal],
+        None,
+    );
+}
+
+#[test]
+fn snapshot_isolation() {
+    check(
+        SimulatedIsolation::SnapshotIsolation,
+        &[Consistency::Causal, Consistency::SnapshotIsolation],
+        None,
+    );
+}
+
+#[test]
+fn serializable() {
+    check(
+        SimulatedIsolation::Serializable,
+        &[Consistency::SnapshotIsolation, Consistency::Serializable, Consistency::StrictSerializable],
+        None,
+    );
+}
+
+#[test]
+fn lost_update_is_caught() {
+    check(
+        SimulatedIsolation::LostUpdate,
+        &[Consistency::Causal],
+        Some(Consistency::SnapshotIsolation),
+    );
+}
+
+#[test]
+fn non_repeatable_snapshot_is_caught() {
+    check(
+        SimulatedIsolation::NonRepeatableSnapshot,
+        &[Consistency::ReadCommitted],
+        Some(Consistency::ReadAtomic),
+    );
+}
+
+#[test]
+fn executions_are_reproducible() {
+    // the attempts only differ by their timing
+    let outcomes = |histories: Vec<History>| -> Vec<Vec<Vec<_>>> {
+        histories
+            .iter()
+            .map(|history| {
+                history
+                    .get_data()
+                    .iter()
+                    .map(|session| session.iter().map(|t| (t.events.clone(), t.success)).collect())
+                    .collect()
+            })
+            .collect()
+    };
+    let first = outcomes(generate_and_run(SimulatedIsolation::Causal));
+    let second = outcomes(generate_and_run(SimulatedIsolation::Causal));
+    assert!(first == second, "two executions of the same histories differ");
+}